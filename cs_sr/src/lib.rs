@@ -0,0 +1,6 @@
+pub mod model;
+pub mod raft;
+pub mod scenario;
+pub mod two_phase_commit;
+
+pub use scenario::{run_scenario, ScenarioConfig, ScenarioReport};
@@ -1,209 +1,997 @@
-mod model;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser};
+use cs_sr::model::*;
+use cs_sr::scenario::*;
+use log::info;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path as FilePath, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
-use model::*;
-use stateright::actor::{ActorModel, Network};
-use stateright::{Checker, Model};
+/// Consensus protocol verification with Stateright
+#[derive(Parser, Debug)]
+#[command(name = "consensus", version, about)]
+// What to run instead of checking one actor model scenario; at most one of these
+#[command(group(ArgGroup::new("command").args(["demo", "config", "direct", "raft", "two_phase_commit", "diagram"])))]
+// Options only the actor model of the PBFT-style protocol takes
+#[command(group(ArgGroup::new("actor_options").multiple(true).args([
+    "byzantine_faults", "fault_selection", "proposer", "proposals", "initial_values", "weights", "symmetry",
+    "liveness_overhead",
+    "partition", "view_change_timeout", "max_retries", "buffer_prepares", "abort_on_view_change", "observers",
+    "allowed_values", "sweep", "dot", "simulate", "properties", "replay",
+]).conflicts_with_all(["demo", "config", "direct", "raft", "two_phase_commit", "diagram"])))]
+// Options either encoding of the PBFT-style protocol takes, but Raft and 2PC don't
+#[command(group(ArgGroup::new("pbft_options").multiple(true).args([
+    "values", "mode", "instances", "quorum_sizes", "deadlocks", "message_counts", "terminal_states",
+]).conflicts_with_all(["demo", "config", "raft", "two_phase_commit", "diagram"])))]
+struct Cli {
+    /// Run the canned demo scenarios instead of a single configured one
+    #[arg(long)]
+    demo: bool,
 
-fn main() {
-    println!("=== Consensus Protocol Verification with Stateright ===\n");
+    /// Run every [[scenario]] of this TOML file (nodes, faults, network, values,
+    /// proposer) instead of a single configured one, and tabulate the outcomes
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
 
-    // Start with smaller scenarios to see state exploration working
-    // Note: With non-deterministic proposals, state space grows rapidly:
-    // - Each node can propose 3 different values (V1, V2, V3)
-    // - Message ordering creates additional states
+    /// Number of nodes in the system
+    #[arg(long, default_value_t = 3)]
+    nodes: usize,
 
-    // Scenario 1: Small system - 3 nodes, no faults (f=1, quorum=3)
-    println!("Scenario 1: Small System - 3 Nodes (No Faults)");
-    run_scenario(3, 0, false);
+    /// Number of faulty nodes
+    #[arg(long, default_value_t = 0)]
+    faults: usize,
 
-    // Scenario 2: Normal operation (5 nodes, no faults)
-    println!("\nScenario 2: Normal Operation - 5 Nodes (No Faults)");
-    run_scenario(5, 0, false);
+    /// How the faulty nodes misbehave; the demo scenarios pick their own
+    #[arg(long, value_enum, default_value_t = FaultKind::Crash, conflicts_with = "demo")]
+    fault_kind: FaultKind,
 
-    // Scenario 3: Single node crash
-    println!("\nScenario 3: Single Node Crash");
-    run_scenario(5, 1, false);
+    /// Number of further faulty nodes, beside --faults, that equivocate whatever
+    /// --fault-kind says, e.g. one crashing next to one equivocating
+    #[arg(long, value_name = "N", default_value_t = 0, conflicts_with = "symmetry")]
+    byzantine_faults: usize,
 
-    println!("\n=== Verification Complete ===");
-    println!("\nNote: State space grows exponentially with:");
-    println!("  - Number of nodes (each can propose)");
-    println!("  - Number of possible values (V1, V2, V3)");
-    println!("  - Message interleaving");
+    /// Which nodes are faulty: "last" (the highest IDs but the proposer's), "first",
+    /// "random=SEED", or a comma-separated list of IDs, whose number replaces --faults;
+    /// the last --byzantine-faults of them equivocate
+    #[arg(long, value_name = "NODES", default_value = "last", value_parser = parse_fault_selection)]
+    fault_selection: FaultSelection,
+
+    /// How the network delivers messages; the demo scenarios pick their own
+    #[arg(long, value_enum, default_value_t = NetworkKind::UnorderedNonduplicating, conflicts_with = "demo")]
+    network: NetworkKind,
+
+    /// Shorthand for --network lossy
+    #[arg(long, conflicts_with_all = ["network", "demo"])]
+    lossy: bool,
+
+    /// Number of distinct values the proposer proposes, from 1 to 255
+    #[arg(long, default_value_t = 3,
+          value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=MAX_VALUES as u64))]
+    values: usize,
+
+    /// Node that proposes the values
+    #[arg(long, default_value_t = 0)]
+    proposer: NodeId,
+
+    /// Values (comma-separated) the proposer proposes in place of the first
+    /// --values of the domain, e.g. just V1 for a quick happy path
+    #[arg(long, value_name = "VALUES", value_delimiter = ',', conflicts_with_all = ["values", "initial_values"])]
+    proposals: Option<Vec<Value>>,
+
+    /// Give nodes inputs of their own, as NODE=VALUE pairs (comma-separated), e.g.
+    /// 0=V1,1=V2; every node with one proposes it instead of --proposer proposing
+    #[arg(long, value_name = "NODE=VALUE", value_delimiter = ',', value_parser = parse_initial_value,
+          conflicts_with = "symmetry")]
+    initial_values: Vec<(NodeId, Value)>,
+
+    /// Weigh PREPARE and COMMIT quorums by stake, as NODE=WEIGHT pairs
+    /// (comma-separated); other nodes weigh 1, and a quorum needs the same share
+    /// of the total stake as of the nodes
+    #[arg(long, value_name = "NODE=WEIGHT", value_delimiter = ',', value_parser = parse_weight,
+          conflicts_with_all = ["symmetry", "quorum_sizes"])]
+    weights: Vec<(NodeId, u64)>,
+
+    /// Decide a single value, or commit a log of --instances values in order;
+    /// selects which agreement property is checked
+    #[arg(long, value_enum, default_value_t = ConsensusMode::SingleDecree)]
+    mode: ConsensusMode,
+
+    /// Number of consensus instances (sequence numbers) decided one after another,
+    /// in log mode
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    instances: u64,
+
+    /// Check the direct ConsensusModel encoding instead of the actor model
+    #[arg(long)]
+    direct: bool,
+
+    /// Check Raft-style leader election instead of the PBFT-style protocol
+    #[arg(long)]
+    raft: bool,
+
+    /// Last term in which a Raft election may start
+    #[arg(long, default_value_t = 2, requires = "raft", value_parser = clap::value_parser!(u64).range(1..))]
+    terms: u64,
+
+    /// Check two-phase commit, node 0 coordinating the others, instead of the
+    /// PBFT-style protocol; --faults crashes the last participants
+    #[arg(long)]
+    two_phase_commit: bool,
+
+    /// How to print scenario reports
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Print nothing but a line for each scenario that was infeasible or violated a
+    /// property, as --format quiet does; either way the exit status is non-zero if any was
+    #[arg(short, long, conflicts_with = "format")]
+    quiet: bool,
+
+    /// Also check with symmetry reduction over interchangeable nodes, and compare;
+    /// the checker only reduces during depth-first search, so that run uses it
+    #[arg(long, conflicts_with = "search")]
+    symmetry: bool,
+
+    /// Check twice, once with only the safety properties and once with liveness
+    /// too, and compare the states explored and the time taken
+    #[arg(long, conflicts_with_all = ["symmetry", "sweep", "dot", "replay"])]
+    liveness_overhead: bool,
+
+    /// Start with these nodes (comma-separated IDs) partitioned from the rest;
+    /// each node heals at an arbitrary point
+    #[arg(long, value_name = "IDS", value_delimiter = ',', conflicts_with = "symmetry")]
+    partition: Option<Vec<NodeId>>,
+
+    /// Nodes believing in a quorum size of their own, as NODE=SIZE pairs
+    /// (comma-separated), e.g. during a membership change
+    #[arg(long, value_name = "NODE=SIZE", value_delimiter = ',', value_parser = parse_quorum_size,
+          conflicts_with = "symmetry")]
+    quorum_sizes: Vec<(NodeId, usize)>,
+
+    /// Let nodes time out on the primary and change views after this delay; the
+    /// timeout only fires once no message this fast or faster is in flight
+    #[arg(long, value_enum, value_name = "CLASS", conflicts_with = "symmetry")]
+    view_change_timeout: Option<DelayClass>,
+
+    /// Nodes (comma-separated IDs) whose messages are slow rather than fast
+    #[arg(long, value_name = "IDS", value_delimiter = ',', requires = "view_change_timeout")]
+    slow: Vec<NodeId>,
+
+    /// Let each node time out up to this many times before deciding, re-sending its
+    /// PROPOSEs and votes each time in case the network lost them
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    max_retries: u8,
+
+    /// Let nodes keep PREPAREs that arrive before the PRE-PREPARE for their value,
+    /// and count them once they accept it
+    #[arg(long)]
+    buffer_prepares: bool,
+
+    /// Let the primary of a new view that holds no value propose BOTTOM, so that
+    /// the nodes may decide to abort the instance rather than a fresh value
+    #[arg(long, requires = "view_change_timeout")]
+    abort_on_view_change: bool,
+
+    /// Nodes (comma-separated IDs) that only learn decisions from DECIDEs, voting in
+    /// no quorum; neither the proposer nor node 0, the first primary
+    #[arg(long, value_name = "IDS", value_delimiter = ',',
+          conflicts_with_all = ["symmetry", "sweep", "view_change_timeout"])]
+    observers: Vec<NodeId>,
+
+    /// Values (comma-separated) messages may carry; nodes drop messages for any
+    /// other, as one from a faulty node might be
+    #[arg(long, value_name = "VALUES", value_delimiter = ',')]
+    allowed_values: Option<Vec<Value>>,
+
+    /// Check the scenario once for every node count from --nodes up to this many,
+    /// and print how the state space grows
+    #[arg(long, value_name = "MAX_NODES", conflicts_with_all = ["symmetry", "partition", "quorum_sizes", "slow"])]
+    sweep: Option<usize>,
+
+    /// After checking, write the explored state graph to this file as Graphviz DOT
+    #[arg(long, value_name = "PATH")]
+    dot: Option<PathBuf>,
+
+    /// Checker worker threads
+    #[arg(long, default_value_t = DEFAULT_THREADS)]
+    threads: usize,
+
+    /// Depth bound for the checker and the --dot export
+    #[arg(long, default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+
+    /// Drop the depth bound and explore the entire reachable state space
+    #[arg(long, conflicts_with = "max_depth")]
+    exhaustive: bool,
+
+    /// Order in which the checker explores states
+    #[arg(long, value_enum, default_value_t = SearchStrategy::Bfs)]
+    search: SearchStrategy,
+
+    /// Instead of searching, visit this many states along random paths, each cut off
+    /// at --max-depth, checking properties as they go; for models too large to search
+    #[arg(long, value_name = "STEPS", value_parser = clap::value_parser!(u64).range(1..),
+          conflicts_with_all = ["symmetry", "search", "sweep", "replay"])]
+    simulate: Option<u64>,
+
+    /// Seed for --simulate; the same seed walks the same paths
+    #[arg(long, default_value_t = 0, requires = "simulate")]
+    seed: u64,
+
+    /// Count deadlocked states: nothing can happen, yet a non-faulty node is undecided.
+    /// Replays the path to every visited state, so checking is much slower.
+    #[arg(long)]
+    deadlocks: bool,
+
+    /// Count the messages of each kind sent along the path that sends the most.
+    /// Replays the path to every visited state, as --deadlocks does
+    #[arg(long)]
+    message_counts: bool,
+
+    /// Count the states in which nothing more can happen, by whether every, some or
+    /// no non-faulty node decided. Replays the path to every visited state, as
+    /// --deadlocks does
+    #[arg(long)]
+    terminal_states: bool,
+
+    /// Check only these of the actor model's properties (comma-separated names, as
+    /// reports print them), e.g. the safety ones without the costlier termination
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = parse_property)]
+    properties: Option<Vec<&'static str>>,
+
+    /// While checking, log the states explored so far every SECS seconds (1 if
+    /// not given), to tell a run that's making headway from one that's exploding
+    #[arg(long, value_name = "SECS", num_args = 0..=1, default_missing_value = "1",
+          value_parser = clap::value_parser!(u64).range(1..))]
+    progress: Option<u64>,
+
+    /// After checking, write each run's metrics to this file in Prometheus text format
+    #[arg(long, value_name = "PATH")]
+    metrics: Option<PathBuf>,
+
+    /// After checking, append one row per run to this CSV file, writing the header
+    /// if the file is new
+    #[arg(long, value_name = "PATH")]
+    csv: Option<PathBuf>,
+
+    /// Instead of checking, re-run a counterexample (one entry of a JSON report's
+    /// "counterexamples") from this file on fresh nodes, printing every step
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["symmetry", "dot", "metrics", "csv", "sweep"])]
+    replay: Option<PathBuf>,
+
+    /// With --replay, first shrink the counterexample to as few steps as still
+    /// violate its property, and print that instead
+    #[arg(long, requires = "replay")]
+    minimize: bool,
+
+    /// Instead of checking, print the protocol's state machine, every phase and
+    /// the transitions out of it, in this notation (dot if not given)
+    #[arg(long, value_enum, value_name = "FORMAT", num_args = 0..=1, default_missing_value = "dot")]
+    diagram: Option<DiagramFormat>,
 }
 
-fn run_scenario(num_nodes: usize, faulty_count: usize, lossy_network: bool) {
-    println!("  Nodes: {}, Faulty: {}, Network: {}",
-             num_nodes,
-             faulty_count,
-             if lossy_network { "Lossy" } else { "Reliable" });
+/// Exits with a failure status if any scenario was infeasible or violated a property
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // Reports go to stdout; scenario headers, progress and files written are logged
+    // to stderr at info level, warnings at warn and node dumps at debug. Only text output logs more
+    // than warnings by default, and RUST_LOG overrides the level
+    let level = if cli.format == OutputFormat::Text && !cli.quiet { "info" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level))
+        .format(|buf, record| match record.level() {
+            log::Level::Error | log::Level::Warn => writeln!(buf, "{}: {}", record.level(), record.args()),
+            _ => writeln!(buf, "{}", record.args()),
+        })
+        .init();
 
-    // Create peer list
-    let peers: Vec<_> = (0..num_nodes).map(|i| stateright::actor::Id::from(i)).collect();
+    if let Some(format) = cli.diagram {
+        if let Err(err) = write_diagram(format, &mut io::stdout().lock()) {
+            eprintln!("error: could not print the diagram: {}", err);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
 
-    // Per TLA+ NodeCrash: Mark last faulty_count nodes as faulty (not including proposer node 0)
-    // This ensures node 0 can still propose
-    let faulty_node_ids: Vec<usize> = if faulty_count > 0 {
-        ((num_nodes - faulty_count)..num_nodes).collect()
+    let config = scenario_config(&cli);
+    let format = config.format;
+    if format == OutputFormat::Text {
+        println!("=== Consensus Protocol Verification with Stateright ===\n");
+    }
+    let reports = if cli.demo {
+        run_demo(&checker_options(&cli, ScenarioConfig::new(cli.nodes, cli.faults)))
+    } else if let Some(path) = &cli.config {
+        run_batch(&read_scenarios(path, &batch_base(&cli, &config)), format)
     } else {
-        Vec::new()
+        if format == OutputFormat::Text {
+            info!("Scenario: {} Nodes, {} Faulty", config.num_nodes, config.faulty_count);
+        }
+        if let Some(path) = &cli.replay {
+            return replay(&config, path, cli.minimize);
+        }
+        run_single(&cli, &config)
     };
+    write_reports(&cli, &reports);
+
+    match format {
+        OutputFormat::Text => {
+            to_stdout(|out| write_conflicts(&reports, out));
+            println!("\n=== Verification Complete ===");
+        }
+        OutputFormat::Quiet => to_stdout(|out| write_failures(&reports, out)),
+        OutputFormat::Json => {}
+    }
+    if reports.iter().all(ScenarioReport::passed) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
 
-    if !faulty_node_ids.is_empty() {
-        println!("  Faulty nodes: {:?}", faulty_node_ids);
+/// Check an actor scenario, logging its header before and printing its report
+/// after in text output, exiting with an error if it can't be built
+fn check(config: &ScenarioConfig) -> ScenarioReport {
+    let text = config.format == OutputFormat::Text;
+    let checked = if text { log_header(config) } else { Ok(()) }.and_then(|()| run_scenario(config));
+    let report = checked.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    });
+    if text {
+        to_stdout(|out| report.write_summary(out));
     }
+    report
+}
 
-    // Configure network
-    // Use UNORDERED network for model checking to explore message interleavings
-    // This creates non-determinism: messages can be delivered in any order
-    let network: Network<MessageType> = if lossy_network {
-        Network::new_unordered_nonduplicating(vec![]) // Can drop messages
+/// Check `config` with another backend than the actor model, as `run`, logging
+/// `header` in place of the actor model's header and exiting with an error if
+/// the backend can't build it
+fn check_other(
+    config: &ScenarioConfig,
+    header: String,
+    run: impl FnOnce(&ScenarioConfig) -> Result<ScenarioReport, String>,
+) -> ScenarioReport {
+    let text = config.format == OutputFormat::Text;
+    if text {
+        info!("{}", header);
+    }
+    let report = run(config).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    });
+    if text {
+        to_stdout(|out| report.write_summary(out));
+    }
+    report
+}
+
+/// Check the canned demo scenarios, each under its title
+fn run_demo(base: &ScenarioConfig) -> Vec<ScenarioReport> {
+    let text = base.format == OutputFormat::Text;
+    let scenarios = demo_scenarios(base);
+    let mut reports = Vec::new();
+    for (index, (title, config)) in scenarios.iter().enumerate() {
+        if text {
+            info!("{}Scenario {}: {}", if index == 0 { "" } else { "\n" }, index + 1, title);
+        }
+        reports.push(check(config));
+    }
+    if text {
+        println!("\nNote: State space grows exponentially with:");
+        println!("  - Number of nodes (each can propose)");
+        println!("  - Number of possible values (num_values)");
+        println!("  - Message interleaving");
+    } else if base.format == OutputFormat::Json {
+        print_json(&reports);
+    }
+    reports
+}
+
+/// Check each scenario of a file in turn, and tabulate the outcomes
+fn run_batch(configs: &[ScenarioConfig], format: OutputFormat) -> Vec<ScenarioReport> {
+    let mut reports = Vec::new();
+    for (index, config) in configs.iter().enumerate() {
+        if format == OutputFormat::Text {
+            info!("\nScenario {} of {}: {} Nodes, {} Faulty",
+                  index + 1,
+                  configs.len(),
+                  config.num_nodes,
+                  config.faulty_count);
+        }
+        reports.push(check(config));
+    }
+    match format {
+        OutputFormat::Text => to_stdout(|out| write_batch(&reports, out)),
+        OutputFormat::Json => print_json(&reports),
+        OutputFormat::Quiet => {}
+    }
+    reports
+}
+
+/// Re-run (and first, if asked, minimize) the counterexample in `path` on the
+/// nodes of `config`, printing every step
+fn replay(config: &ScenarioConfig, path: &FilePath, minimize: bool) -> ExitCode {
+    let (text, json) = (config.format == OutputFormat::Text, config.format == OutputFormat::Json);
+    let mut trace = read_trace(path);
+    if minimize {
+        let property = trace.property.clone().unwrap_or_else(|| {
+            eprintln!("error: {} records no property to minimize against", path.display());
+            std::process::exit(1);
+        });
+        trace.steps = minimize_trace(config, &trace.steps, &property).unwrap_or_else(|err| {
+            eprintln!("error: {}: {}", path.display(), err);
+            std::process::exit(1);
+        });
+        if json {
+            print_json(&trace);
+            return ExitCode::SUCCESS;
+        }
+    }
+    let replayed = replay_trace(config, &trace.steps);
+    if text {
+        // Up to the step that diverged, but not one naming a stranger
+        let taken = match &replayed {
+            Ok(_) => trace.steps.len(),
+            Err(ReplayError::Invalid(_)) => 0,
+            Err(ReplayError::Diverged(step)) => step + 1,
+            Err(ReplayError::UnknownNode { step, .. }) => *step,
+        };
+        to_stdout(|out| {
+            for (index, step) in trace.steps[..taken].iter().enumerate() {
+                step.write_step(index + 1, out)?;
+            }
+            Ok(())
+        });
+    }
+    let states = replayed.unwrap_or_else(|err| {
+        eprintln!("error: {}: {}", path.display(), err);
+        std::process::exit(1);
+    });
+    if text {
+        #[cfg(feature = "timeline")]
+        to_stdout(|out| write_timelines(&states, out));
+        println!("\n=== Replay Complete ===");
+    } else if json {
+        print_json(&states);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Check the single scenario of `config` in whichever mode the command line asks
+/// for, then export its state graph if asked to
+fn run_single(cli: &Cli, config: &ScenarioConfig) -> Vec<ScenarioReport> {
+    let (text, json) = (config.format == OutputFormat::Text, config.format == OutputFormat::Json);
+    let single = |report: ScenarioReport| {
+        if json {
+            print_json(&report);
+        }
+        vec![report]
+    };
+    let reports = if cli.raft {
+        let header = format!("  Nodes: {}, Crashed: {}, Terms: {}, Network: {:?} (Raft election)",
+                             config.num_nodes,
+                             config.faulty_count,
+                             cli.terms,
+                             config.network);
+        single(check_other(config, header, |config| run_raft_scenario(config, cli.terms)))
+    } else if cli.two_phase_commit {
+        let header = format!("  Nodes: 1 coordinator + {} participants, Crashed: {}, Network: {:?} (two-phase commit)",
+                             config.num_nodes - 1,
+                             config.faulty_count,
+                             config.network);
+        single(check_other(config, header, run_tpc_scenario))
+    } else if cli.direct {
+        let header = format!("  Nodes: {}, Max crashes: {}, Values: {} (direct model)",
+                             config.num_nodes,
+                             config.faulty_count,
+                             config.num_values);
+        single(check_other(config, header, run_direct_scenario))
+    } else if let Some(max_nodes) = cli.sweep {
+        let reports: Vec<_> = sweep_scenarios(config, max_nodes)
+            .iter()
+            .map(|config| {
+                if text {
+                    info!("\nSweep: {} Nodes", config.num_nodes);
+                }
+                check(config)
+            })
+            .collect();
+        if text {
+            to_stdout(|out| write_sweep(&reports, out));
+        } else if json {
+            print_json(&reports);
+        }
+        reports
+    } else if cli.symmetry {
+        let baseline = check(config);
+        let reduced = check(&ScenarioConfig { symmetry: true, ..config.clone() });
+        if text {
+            println!("  Symmetry reduction: {} -> {} unique states",
+                     baseline.unique_states,
+                     reduced.unique_states);
+        }
+        let reports = vec![baseline, reduced];
+        if json {
+            print_json(&reports);
+        }
+        reports
+    } else if cli.liveness_overhead {
+        let safety = check(&config.safety_only());
+        let full = check(config);
+        if text {
+            println!("  Liveness overhead: {} states in {} ms with safety only, {} states in {} ms with liveness",
+                     safety.states_explored,
+                     safety.elapsed_ms,
+                     full.states_explored,
+                     full.elapsed_ms);
+        }
+        if json {
+            // Name the reports rather than leave their order to say which is which
+            #[derive(Serialize)]
+            struct Overhead<'a> {
+                safety: &'a ScenarioReport,
+                full: &'a ScenarioReport,
+            }
+            print_json(&Overhead { safety: &safety, full: &full });
+        }
+        vec![safety, full]
     } else {
-        Network::new_unordered_nonduplicating(vec![]) // Reliable but unordered
+        single(check(config))
     };
 
-    // Calculate quorum size: For Byzantine fault tolerance with f faults,
-    // we need at least 2f + 1 nodes, and quorum = 2f + 1 = num_nodes - f
-    // For crash fault tolerance: quorum = floor(n/2) + 1
-    // Using Byzantine formula: quorum = num_nodes (all nodes must agree for simplicity)
-    let quorum_size = num_nodes;
-
-    // Create actor model
-    // ActorModel::new(capacity, cfg) where capacity is the number of actor IDs to support
-    // We register the actor template ONCE, and it's instantiated for each ID
-    let model = ActorModel::<ConsensusActor, usize>::new(num_nodes, ())
-        .actor(ConsensusActor::with_faults(
-            peers.clone(),
-            faulty_node_ids.clone(),
-            quorum_size,
-        ))
-        .init_network(network)
-        .property(stateright::Expectation::Always, "no crashes during init", |_, state| {
-            // Simple property to verify model is working
-            state.actor_states.iter().all(|s| s.state != NodeState::Failed || s.is_faulty)
+    if let Some(path) = &cli.dot {
+        let depth = config.depth_bound().unwrap_or(usize::MAX);
+        let model = build_actor_model(config).unwrap_or_else(|err| {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
         });
+        let states = write_file(path, |file| write_dot(&model, depth, file));
+        info!("Wrote {} states to {}", states, path.display());
+    }
+    reports
+}
+
+/// Write the reports' metrics and CSV rows to the files the command line names
+fn write_reports(cli: &Cli, reports: &[ScenarioReport]) {
+    if let Some(path) = &cli.metrics {
+        write_file(path, |file| write_metrics(reports, file));
+        info!("Wrote metrics for {} run(s) to {}", reports.len(), path.display());
+    }
+    if let Some(path) = &cli.csv {
+        let header = std::fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+        append_file(path, |file| write_csv(reports, file, header));
+        info!("Appended {} run(s) to {}", reports.len(), path.display());
+    }
+}
 
-    // Run bounded model checker
-    println!("  Running model checker...");
-    let checker = model.checker()
-        .threads(4)
-        .target_max_depth(20)
-        .spawn_bfs()
-        .join();
-
-    // Report model checking results
-    println!("  ✓ Model checking complete!");
-    println!("    States explored: {}", checker.state_count());
-    println!("    Max depth: {}", checker.max_depth());
-
-    // Debug: Check if model has the right number of actors
-    println!("    DEBUG: Model has {} actor slots", num_nodes);
-}
-
-/// Simulate a specific fault scenario
-#[allow(dead_code)]
-fn simulate_fault_scenario() {
-    println!("\n=== Detailed Fault Scenario Simulation ===\n");
-
-    // Create a scenario where we manually inject faults
-    let num_nodes = 5;
-    let _peers: Vec<_> = (0..num_nodes).map(|i| stateright::actor::Id::from(i)).collect();
-
-    println!("Simulating: Node 0 proposes V1, Node 3 crashes after PREPARE phase");
-    
-    // Manual state evolution
-    let mut states: Vec<ConsensusNodeState> = (0..num_nodes)
-        .map(|i| ConsensusNodeState::new(i, 5))
-        .collect();
-
-    // Step 1: Node 0 proposes V1
-    println!("\n[Step 1] Node 0 proposes V1");
-    states[0].value = Some(Value::V1);
-    print_states(&states);
-
-    // Step 2: All nodes receive proposal and send PREPARE
-    println!("\n[Step 2] All nodes receive proposal and broadcast PREPARE");
-    for i in 0..num_nodes {
-        states[i].value = Some(Value::V1);
-        *states[i].prepare_count.entry(Value::V1).or_insert(0) = num_nodes;
-        states[i].state = NodeState::Prepared;
-    }
-    print_states(&states);
-
-    // Step 3: Node 3 crashes
-    println!("\n[Step 3] Node 3 crashes");
-    states[3].is_faulty = true;
-    states[3].state = NodeState::Failed;
-    print_states(&states);
-
-    // Step 4: Non-faulty nodes receive COMMITs (only 4 nodes now)
-    println!("\n[Step 4] Remaining 4 nodes broadcast COMMIT");
-    for i in 0..num_nodes {
-        if !states[i].is_faulty {
-            *states[i].commit_count.entry(Value::V1).or_insert(0) = 4;
-            // Can't reach quorum of 5 with only 4 nodes!
-        }
-    }
-    print_states(&states);
-
-    // Analysis
-    println!("\n[Analysis]");
-    println!("  Quorum required: 5 nodes");
-    println!("  Active nodes: 4 nodes");
-    println!("  Result: DEADLOCK - Cannot reach consensus!");
-    println!("  Lesson: With f=2 Byzantine tolerance, we need 2f+1=5 nodes.");
-    println!("          Losing 1 node means we can't tolerate any more faults.");
-}
-
-#[allow(dead_code)]
-fn print_states(states: &[ConsensusNodeState]) {
-    for state in states {
-        println!(
-            "  Node {}: state={:?}, value={:?}, prepares={}, commits={}, decided={}, faulty={}",
-            state.id,
-            state.state,
-            state.value,
-            state.prepare_count.get(&Value::V1).unwrap_or(&0),
-            state.commit_count.get(&Value::V1).unwrap_or(&0),
-            state.decided,
-            state.is_faulty
-        );
+/// Check what clap can't about the command line, exiting with an error if it's
+/// inconsistent, and turn it into the configuration of a single scenario
+fn scenario_config(cli: &Cli) -> ScenarioConfig {
+    if cli.nodes == 0 {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, "--nodes must be at least 1")
+            .exit();
+    }
+    if cli.threads == 0 {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, "--threads must be at least 1")
+            .exit();
+    }
+    if cli.max_depth == 0 {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, "--max-depth must be at least 1")
+            .exit();
+    }
+    if cli.proposer >= cli.nodes {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("--proposer ({}) must be less than --nodes ({})", cli.proposer, cli.nodes),
+            )
+            .exit();
+    }
+    let faults = match &cli.fault_selection {
+        FaultSelection::Explicit(nodes) => nodes.len().saturating_sub(cli.byzantine_faults),
+        _ => cli.faults,
+    };
+    if faults >= cli.nodes {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("--faults ({}) must be less than --nodes ({})", faults, cli.nodes),
+            )
+            .exit();
+    }
+    if faults + cli.byzantine_faults >= cli.nodes {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("--faults and --byzantine-faults ({}) must be less than --nodes ({})",
+                        faults + cli.byzantine_faults,
+                        cli.nodes),
+            )
+            .exit();
+    }
+    if let FaultSelection::Explicit(nodes) = &cli.fault_selection {
+        if nodes.iter().any(|&node| node >= cli.nodes) || nodes.iter().collect::<BTreeSet<_>>().len() < nodes.len() {
+            Cli::command()
+                .error(
+                    ErrorKind::ValueValidation,
+                    format!("--fault-selection IDs must be distinct and less than --nodes ({})", cli.nodes),
+                )
+                .exit();
+        }
+        if nodes.len() < cli.byzantine_faults {
+            Cli::command()
+                .error(ErrorKind::ValueValidation, "--fault-selection must list every --byzantine-faults node")
+                .exit();
+        }
     }
+
+    if cli.sweep.is_some_and(|max_nodes| max_nodes < cli.nodes) {
+        Cli::command()
+            .error(ErrorKind::ValueValidation, format!("--sweep must be at least --nodes ({})", cli.nodes))
+            .exit();
+    }
+
+    let network = if cli.lossy { NetworkKind::Lossy } else { cli.network };
+    if cli.direct && network != NetworkKind::UnorderedNonduplicating {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--direct only models an unordered, non-duplicating network")
+            .exit();
+    }
+    if cli.direct && !matches!(cli.fault_kind, FaultKind::Crash | FaultKind::LyingLeader) {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--direct only models crashes and a lying leader")
+            .exit();
+    }
+    if (cli.raft || cli.two_phase_commit) && cli.fault_kind != FaultKind::Crash {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--raft and --two-phase-commit only model crashes")
+            .exit();
+    }
+    if cli.direct && (cli.instances > 1 || cli.mode == ConsensusMode::Log) {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--direct only models a single consensus instance")
+            .exit();
+    }
+    if cli.instances > 1 && cli.mode == ConsensusMode::SingleDecree {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--instances above 1 needs --mode log")
+            .exit();
+    }
+
+    let partition = cli.partition.as_ref().map(|group| Partition { group: group.iter().copied().collect() });
+    let quorum_sizes: HashMap<NodeId, usize> = cli.quorum_sizes.iter().copied().collect();
+    if quorum_sizes.iter().any(|(&node, &size)| node >= cli.nodes || size == 0 || size > cli.nodes) {
+        Cli::command()
+            .error(
+                ErrorKind::ValueValidation,
+                format!("--quorum-sizes needs nodes below --nodes ({0}) and sizes from 1 to {0}", cli.nodes),
+            )
+            .exit();
+    }
+
+    let weights: HashMap<NodeId, u64> = cli.weights.iter().copied().collect();
+    let initial_values: HashMap<NodeId, Value> = cli.initial_values.iter().cloned().collect();
+    let observers: BTreeSet<NodeId> = cli.observers.iter().copied().collect();
+    let allowed_values: Option<BTreeSet<Value>> =
+        cli.allowed_values.as_ref().map(|values| values.iter().cloned().collect());
+    let delays = cli.view_change_timeout.map(|view_change_timeout| Delays {
+        slow_nodes: cli.slow.iter().copied().collect(),
+        view_change_timeout,
+    });
+
+    let config = ScenarioConfig {
+        fault_kind: cli.fault_kind,
+        byzantine_faults: cli.byzantine_faults,
+        // The direct model's lying leader is node 0, and its crashes strike any node
+        fault_selection: if cli.direct { FaultSelection::First } else { cli.fault_selection.clone() },
+        network,
+        num_values: cli.values,
+        proposals: cli.proposals.clone(),
+        mode: cli.mode,
+        num_instances: cli.instances,
+        proposer_id: cli.proposer,
+        partition,
+        quorum_sizes,
+        initial_values,
+        weights,
+        delays,
+        max_retries: cli.max_retries,
+        buffer_prepares: cli.buffer_prepares,
+        abort_on_view_change: cli.abort_on_view_change,
+        observers,
+        allowed_values,
+        simulation: cli.simulate.map(|steps| Simulation { steps: steps as usize, seed: cli.seed }),
+        ..checker_options(cli, ScenarioConfig::new(cli.nodes, faults))
+    };
+    // Whether the actor model can be built, with what --symmetry adds to the baseline
+    // run; the other backends take only what is checked above, and the demo and
+    // scenario files build their own
+    let actor_scenario = !(cli.demo || cli.config.is_some() || cli.direct || cli.raft || cli.two_phase_commit);
+    let reduced = ScenarioConfig { symmetry: cli.symmetry, ..config.clone() };
+    if let (true, Err(reason)) = (actor_scenario, reduced.validate()) {
+        Cli::command().error(ErrorKind::ValueValidation, reason).exit();
+    }
+    config
+}
+
+/// What the scenarios of a file leave out: the network and fault kind of `config`
+/// besides the checker and output options
+fn batch_base(cli: &Cli, config: &ScenarioConfig) -> ScenarioConfig {
+    let base = ScenarioConfig::new(cli.nodes, cli.faults);
+    checker_options(cli, ScenarioConfig { network: config.network, fault_kind: config.fault_kind, ..base })
+}
+
+/// The checker and output options of the command line on top of `base`: all the
+/// demo and scenario files take from it
+fn checker_options(cli: &Cli, base: ScenarioConfig) -> ScenarioConfig {
+    ScenarioConfig {
+        threads: cli.threads,
+        max_depth: cli.max_depth,
+        exhaustive: cli.exhaustive,
+        search: cli.search,
+        deadlocks: cli.deadlocks,
+        message_counts: cli.message_counts,
+        terminal_states: cli.terminal_states,
+        properties: cli.properties.clone(),
+        progress: cli.progress.map(Duration::from_secs),
+        format: if cli.quiet { OutputFormat::Quiet } else { cli.format },
+        ..base
+    }
+}
+
+/// Parse one NODE=SIZE entry of --quorum-sizes
+fn parse_quorum_size(entry: &str) -> Result<(NodeId, usize), String> {
+    let (node, size) = entry.split_once('=').ok_or("expected NODE=SIZE")?;
+    let node = node.parse().map_err(|err| format!("invalid node {:?}: {}", node, err))?;
+    let size = size.parse().map_err(|err| format!("invalid quorum size {:?}: {}", size, err))?;
+    Ok((node, size))
+}
+
+/// Parse --fault-selection: a strategy name or the faulty nodes' IDs
+fn parse_fault_selection(selection: &str) -> Result<FaultSelection, String> {
+    match selection {
+        "last" => Ok(FaultSelection::Last),
+        "first" => Ok(FaultSelection::First),
+        _ => {
+            if let Some(seed) = selection.strip_prefix("random=") {
+                let seed = seed.parse().map_err(|err| format!("invalid seed {:?}: {}", seed, err))?;
+                return Ok(FaultSelection::Random(seed));
+            }
+            let nodes = selection
+                .split(',')
+                .map(|node| node.parse().map_err(|err| format!("invalid node {:?}: {}", node, err)));
+            Ok(FaultSelection::Explicit(nodes.collect::<Result<_, String>>()?))
+        }
+    }
+}
+
+/// Parse one NODE=WEIGHT entry of --weights
+fn parse_weight(entry: &str) -> Result<(NodeId, u64), String> {
+    let (node, weight) = entry.split_once('=').ok_or("expected NODE=WEIGHT")?;
+    let node = node.parse().map_err(|err| format!("invalid node {:?}: {}", node, err))?;
+    let weight = weight.parse().map_err(|err| format!("invalid weight {:?}: {}", weight, err))?;
+    Ok((node, weight))
+}
+
+/// Parse one name of --properties into the actor model property's own
+fn parse_property(name: &str) -> Result<&'static str, String> {
+    let names: Vec<_> = consensus_properties().into_iter().map(|p| p.name).collect();
+    names.iter().copied().find(|&known| known == name).ok_or_else(|| {
+        format!("unknown property {:?}; expected one of: {}", name, names.join(", "))
+    })
+}
+
+/// Parse one NODE=VALUE entry of --initial-values
+fn parse_initial_value(entry: &str) -> Result<(NodeId, Value), String> {
+    let (node, value) = entry.split_once('=').ok_or("expected NODE=VALUE")?;
+    let node = node.parse().map_err(|err| format!("invalid node {:?}: {}", node, err))?;
+    Ok((node, value.parse()?))
+}
+
+/// Create `path` and fill it with `write`, exiting with an error if either fails
+fn write_file<T>(path: &FilePath, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<T>) -> T {
+    fill_file(path, File::create(path), write)
+}
+
+/// Add to the end of `path` with `write`, creating it if need be, as `write_file` does
+fn append_file<T>(path: &FilePath, write: impl FnOnce(&mut BufWriter<File>) -> io::Result<T>) -> T {
+    fill_file(path, OpenOptions::new().create(true).append(true).open(path), write)
+}
+
+fn fill_file<T>(
+    path: &FilePath,
+    file: io::Result<File>,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<T>,
+) -> T {
+    let written = file.map(BufWriter::new).and_then(|mut file| {
+        let result = write(&mut file)?;
+        file.flush()?;
+        Ok(result)
+    });
+    written.unwrap_or_else(|err| {
+        eprintln!("error: could not write {}: {}", path.display(), err);
+        std::process::exit(1);
+    })
+}
+
+/// Write to stdout with `write`, panicking as `println!` does if that fails
+fn to_stdout(write: impl FnOnce(&mut io::StdoutLock<'static>) -> io::Result<()>) {
+    write(&mut io::stdout().lock()).expect("failed printing to stdout");
+}
+
+/// Write a report (or list of reports) to stdout as JSON
+fn print_json<T: Serialize>(report: &T) {
+    let json = serde_json::to_string_pretty(report).expect("reports always serialize");
+    println!("{}", json);
+}
+
+/// Read a `Trace` from `path`, exiting with an error if it can't be read or parsed
+fn read_trace(path: &FilePath) -> Trace {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|err| err.to_string()));
+    parsed.unwrap_or_else(|err| {
+        eprintln!("error: could not read a trace from {}: {}", path.display(), err);
+        std::process::exit(1);
+    })
+}
+
+/// Read the scenarios of a TOML file on top of `base`, exiting with an error if
+/// it can't be read or parsed
+fn read_scenarios(path: &FilePath, base: &ScenarioConfig) -> Vec<ScenarioConfig> {
+    let parsed = std::fs::read_to_string(path)
+        .map_err(|err| err.to_string())
+        .and_then(|toml| parse_scenarios(&toml, base));
+    parsed.unwrap_or_else(|err| {
+        eprintln!("error: could not read scenarios from {}: {}", path.display(), err);
+        std::process::exit(1);
+    })
 }
 
 #[cfg(test)]
-mod integration_tests {
+mod tests {
     use super::*;
 
     #[test]
-    fn test_consensus_model() {
-        let model = ConsensusModel::new(5, 2);
-        
-        // Test agreement checker
-        let mut state1 = ConsensusNodeState::new(0, 3);
-        state1.decided = true;
-        state1.value = Some(Value::V1);
-        
-        let mut state2 = ConsensusNodeState::new(1, 3);
-        state2.decided = true;
-        state2.value = Some(Value::V1);
-        
-        assert!(model.check_agreement(&[state1, state2]));
+    fn test_parse_entries() {
+        assert_eq!(parse_quorum_size("2=3"), Ok((2, 3)));
+        assert!(parse_quorum_size("2").is_err());
+        assert!(parse_quorum_size("2=many").is_err());
+        assert_eq!(parse_initial_value("1=V3"), Ok((1, Value::V3)));
+        assert!(parse_initial_value("1").is_err());
+        assert!(parse_initial_value("1=V0").is_err());
+        assert_eq!(parse_weight("0=3"), Ok((0, 3)));
+        assert!(parse_weight("0=-1").is_err());
+        assert_eq!(parse_fault_selection("first"), Ok(FaultSelection::First));
+        assert_eq!(parse_fault_selection("random=7"), Ok(FaultSelection::Random(7)));
+        assert_eq!(parse_fault_selection("0,2"), Ok(FaultSelection::Explicit(vec![0, 2])));
+        assert!(parse_fault_selection("middle").is_err());
+        assert_eq!(parse_property("no overcounting"), Ok("no overcounting"));
+        assert!(parse_property("liveness").is_err());
     }
 
     #[test]
-    fn test_no_premature_decision() {
-        let model = ConsensusModel::new(5, 2);
-        
-        let mut state = ConsensusNodeState::new(0, 3);
-        state.state = NodeState::Decided;
-        state.value = Some(Value::V1);
-        state.commit_count.insert(Value::V1, 2);
-        
-        // Should fail - not enough commits
-        assert!(!model.check_no_premature_decision(&state));
-        
-        // Fix it
-        state.commit_count.insert(Value::V1, 3);
-        assert!(model.check_no_premature_decision(&state));
-    }
-}
\ No newline at end of file
+    fn test_values() {
+        // At least one value to propose, and one per u8 beyond V1 to V3 at most
+        assert_eq!(Cli::try_parse_from(["consensus", "--values", "255"]).unwrap().values, 255);
+        assert!(Cli::try_parse_from(["consensus", "--values", "256"]).is_err());
+        assert!(Cli::try_parse_from(["consensus", "--values", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_quiet() {
+        assert!(Cli::try_parse_from(["consensus", "-q"]).unwrap().quiet);
+        assert!(Cli::try_parse_from(["consensus", "--quiet", "--format", "json"]).is_err());
+        assert_eq!(Cli::try_parse_from(["consensus", "--format", "quiet"]).unwrap().format, OutputFormat::Quiet);
+    }
+
+    #[test]
+    fn test_progress() {
+        assert_eq!(Cli::try_parse_from(["consensus"]).unwrap().progress, None);
+        assert_eq!(Cli::try_parse_from(["consensus", "--progress"]).unwrap().progress, Some(1));
+        assert_eq!(Cli::try_parse_from(["consensus", "--progress", "10"]).unwrap().progress, Some(10));
+        assert!(Cli::try_parse_from(["consensus", "--progress", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_diagram() {
+        assert_eq!(Cli::try_parse_from(["consensus"]).unwrap().diagram, None);
+        assert_eq!(Cli::try_parse_from(["consensus", "--diagram"]).unwrap().diagram, Some(DiagramFormat::Dot));
+        assert_eq!(Cli::try_parse_from(["consensus", "--diagram", "mermaid"]).unwrap().diagram,
+                   Some(DiagramFormat::Mermaid));
+        assert!(Cli::try_parse_from(["consensus", "--diagram", "--direct"]).is_err());
+    }
+
+    #[test]
+    fn test_modes() {
+        // At most one mode, each taking only the options its model uses
+        let parse = |args: &[&str]| Cli::try_parse_from(["consensus"].iter().chain(args));
+        assert!(parse(&["--raft", "--two-phase-commit"]).is_err());
+        assert!(parse(&["--demo", "--config", "scenarios.toml"]).is_err());
+        assert!(parse(&["--demo", "--network", "ordered"]).is_err());
+        assert!(parse(&["--demo", "--lossy"]).is_err());
+        assert!(parse(&["--demo", "--fault-kind", "mute"]).is_err());
+        assert!(parse(&["--two-phase-commit", "--values", "3"]).is_err());
+        assert!(parse(&["--raft", "--mode", "log"]).is_err());
+        assert!(parse(&["--direct", "--symmetry"]).is_err());
+        assert!(parse(&["--direct", "--values", "1", "--quorum-sizes", "0=2"]).is_ok());
+        assert!(parse(&["--two-phase-commit", "--faults", "1", "--lossy", "--exhaustive"]).is_ok());
+        assert!(parse(&["--values", "1", "--symmetry", "--deadlocks"]).is_ok());
+    }
+
+    #[test]
+    fn test_batch_base() {
+        // A scenario file leaves out the network and fault kind for the command line to fill in
+        let args = ["consensus", "--config", "f.toml", "--network", "ordered", "--fault-kind", "mute"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        let base = batch_base(&cli, &scenario_config(&cli));
+        assert_eq!((base.network, base.fault_kind), (NetworkKind::Ordered, FaultKind::Mute));
+        let cli = Cli::try_parse_from(["consensus", "--config", "f.toml", "--lossy"]).unwrap();
+        assert_eq!(batch_base(&cli, &scenario_config(&cli)).network, NetworkKind::Lossy);
+    }
+
+    #[test]
+    fn test_symmetry() {
+        // Symmetry reduction searches depth-first, so --search has no say
+        assert!(Cli::try_parse_from(["consensus", "--symmetry"]).is_ok());
+        assert!(Cli::try_parse_from(["consensus", "--symmetry", "--search", "bfs"]).is_err());
+        assert!(Cli::try_parse_from(["consensus", "--symmetry", "--search", "dfs"]).is_err());
+    }
+
+    #[test]
+    fn test_proposals() {
+        let cli = Cli::try_parse_from(["consensus", "--proposals", "V2,bottom"]).unwrap();
+        assert_eq!(cli.proposals, Some(vec![Value::V2, Value::Bottom]));
+        assert_eq!(scenario_config(&cli).proposals, cli.proposals);
+        assert!(Cli::try_parse_from(["consensus", "--proposals", "V1", "--values", "2"]).is_err());
+        assert!(Cli::try_parse_from(["consensus", "--proposals", "V1", "--initial-values", "1=V2"]).is_err());
+        assert!(Cli::try_parse_from(["consensus", "--proposals", "V1", "--direct"]).is_err());
+    }
+
+    #[test]
+    fn test_liveness_overhead() {
+        assert!(Cli::try_parse_from(["consensus", "--liveness-overhead"]).unwrap().liveness_overhead);
+        let properties = ["consensus", "--liveness-overhead", "--properties", "agreement,termination"];
+        assert!(Cli::try_parse_from(properties).is_ok());
+        assert!(Cli::try_parse_from(["consensus", "--liveness-overhead", "--symmetry"]).is_err());
+    }
+
+    #[test]
+    fn test_replay_json() {
+        // A counterexample saved from a JSON report replays, and the nodes it leaves,
+        // vote counts keyed by instance and value included, print as JSON in turn
+        let delays = Delays { slow_nodes: BTreeSet::from([1]), view_change_timeout: DelayClass::Fast };
+        let config = ScenarioConfig {
+            num_values: 1,
+            delays: Some(delays),
+            format: OutputFormat::Json,
+            ..ScenarioConfig::new(2, 0)
+        };
+        let report = run_scenario(&config).unwrap();
+        let path = std::env::temp_dir().join(format!("consensus-replay-{}.json", std::process::id()));
+        write_file(&path, |file| serde_json::to_writer(file, &report.counterexamples[0]).map_err(io::Error::from));
+        let trace = read_trace(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let states = replay_trace(&config, &trace.steps).unwrap();
+        assert!(states.iter().any(|s| !s.prepare_count.is_empty()));
+        let json = serde_json::to_string_pretty(&states).unwrap();
+        assert!(json.contains("\"prepare_count\": [\n      [\n        [\n          1,\n          \"V1\""));
+    }
+
+    #[test]
+    fn test_abort_on_view_change() {
+        assert!(Cli::try_parse_from(["consensus", "--abort-on-view-change"]).is_err());
+        let cli =
+            Cli::try_parse_from(["consensus", "--abort-on-view-change", "--view-change-timeout", "fast"]).unwrap();
+        assert!(cli.abort_on_view_change);
+        assert_eq!(parse_initial_value("1=bottom"), Ok((1, Value::Bottom)));
+    }
+}
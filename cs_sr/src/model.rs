@@ -1,8 +1,11 @@
 use serde::{Deserialize, Serialize};
 use stateright::actor::*;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use stateright::{Checker, Model, Path, Property, Rewrite, RewritePlan};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Debug, Display};
 use std::hash::Hash;
+use std::sync::{Arc, Mutex};
 
 /// Node ID type
 pub type NodeId = usize;
@@ -10,369 +13,3920 @@ pub type NodeId = usize;
 /// Possible values for consensus
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum Value {
+    /// No value, decided on purpose: deciding it aborts the instance, where a
+    /// node's `value` of None only means it has none yet. Sorts before the others.
+    Bottom,
     V1,
     V2,
     V3,  // Additional value for more realistic testing
+    Custom(u8),  // Values beyond V3, numbered from 4 (V4, V5, ...)
 }
 
-/// Node states in the consensus protocol
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+/// How many distinct values there are to propose, V1 to V255
+pub const MAX_VALUES: usize = u8::MAX as usize;
+
+impl Value {
+    /// The first `num_values` values: V1, V2, V3, then Custom(4), Custom(5), ...
+    /// Panics beyond `MAX_VALUES`.
+    pub fn domain(num_values: usize) -> Vec<Value> {
+        (1..=num_values)
+            .map(|i| match i {
+                1 => Value::V1,
+                2 => Value::V2,
+                3 => Value::V3,
+                _ => Value::Custom(u8::try_from(i).expect("at most MAX_VALUES values supported")),
+            })
+            .collect()
+    }
+
+    /// Whether deciding this value aborts the instance
+    pub fn is_bottom(&self) -> bool {
+        *self == Value::Bottom
+    }
+}
+
+/// What consensus is reached on: `Value` by default, but integers, strings or a
+/// type of the caller's own will do as well
+pub trait ConsensusValue: Clone + Debug + Eq + Hash + Ord + Serialize + Send + Sync + 'static {}
+
+impl<V: Clone + Debug + Eq + Hash + Ord + Serialize + Send + Sync + 'static> ConsensusValue for V {}
+
+impl std::str::FromStr for Value {
+    type Err = String;
+
+    /// Parse a value as printed, V1 to V3, V4 onwards for the custom ones, or
+    /// BOTTOM in any case; also Custom(N), as custom values below 4 print
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("bottom") {
+            return Ok(Value::Bottom);
+        }
+        if let Some(n) = s.strip_prefix("Custom(").and_then(|rest| rest.strip_suffix(')')) {
+            return n.parse().map(Value::Custom).map_err(|err| format!("invalid value {:?}: {}", s, err));
+        }
+        let number = s
+            .strip_prefix(['V', 'v'])
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| (1..=MAX_VALUES).contains(&n))
+            .ok_or_else(|| format!("invalid value {:?}, expected V1 to V255 or BOTTOM", s))?;
+        Ok(Value::domain(number).pop().expect("the domain of a positive count is non-empty"))
+    }
+}
+
+/// BOTTOM, V1, V2, V3, then V4 onwards, as `from_str` parses them back. Custom
+/// values below 4 print as Custom(N), so as not to pass for V1 to V3
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bottom => write!(f, "BOTTOM"),
+            Value::V1 => write!(f, "V1"),
+            Value::V2 => write!(f, "V2"),
+            Value::V3 => write!(f, "V3"),
+            Value::Custom(n) if *n < 4 => write!(f, "Custom({})", n),
+            Value::Custom(n) => write!(f, "V{}", n),
+        }
+    }
+}
+
+/// Node states in the consensus protocol, ordered by how far along a view a node
+/// is: a view change can start from any phase before a decision, a DECIDE still
+/// counts while changing views, and a crash ends everything
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum NodeState {
     Init,
+    PrePrepared,
     Prepared,
     Committed,
+    ViewChanging,
     Decided,
     Failed,
 }
 
-/// Message types in the protocol
+/// The phase in capitals, as the protocol's messages are named
+impl Display for NodeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            NodeState::Init => "INIT",
+            NodeState::PrePrepared => "PRE-PREPARED",
+            NodeState::Prepared => "PREPARED",
+            NodeState::Committed => "COMMITTED",
+            NodeState::ViewChanging => "VIEW-CHANGING",
+            NodeState::Decided => "DECIDED",
+            NodeState::Failed => "FAILED",
+        })
+    }
+}
+
+/// What fires a transition of the protocol's state machine
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Trigger {
+    /// Delivery of the named message
+    Message(&'static str),
+    /// One of the node's own timers going off
+    Timer(&'static str),
+}
+
+/// One edge of the protocol's state machine, as `on_msg` and `on_timeout` take
+/// it: the phase a node moves between, what fires the move and when, and what
+/// the node broadcasts as it goes
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Transition {
+    pub from: NodeState,
+    pub to: NodeState,
+    pub trigger: Trigger,
+    pub condition: &'static str,
+    pub sends: Option<&'static str>,
+}
+
+impl NodeState {
+    /// Every phase, in order
+    pub const ALL: [NodeState; 7] = [
+        NodeState::Init,
+        NodeState::PrePrepared,
+        NodeState::Prepared,
+        NodeState::Committed,
+        NodeState::ViewChanging,
+        NodeState::Decided,
+        NodeState::Failed,
+    ];
+
+    /// The transitions out of this phase. A NEW-VIEW for a later view is taken
+    /// in any undecided phase, but is only listed from VIEW-CHANGING, where a
+    /// node following the protocol waits for it.
+    pub fn transitions(&self) -> Vec<Transition> {
+        use NodeState::*;
+        let edge = |to, trigger, condition, sends| Transition { from: self.clone(), to, trigger, condition, sends };
+        let view_change = || edge(ViewChanging, Trigger::Timer("view change"), "undecided", Some("VIEW-CHANGE"));
+        let crash = || edge(Failed, Trigger::Timer("crash"), "faulty node", None);
+        match self {
+            Init => vec![
+                edge(PrePrepared,
+                     Trigger::Message("PRE-PREPARE"),
+                     "from the view's primary, for a value the node may prepare",
+                     Some("PREPARE")),
+                edge(Prepared,
+                     Trigger::Message("PREPARE"),
+                     "quorum for a value the node's lock kept it from preparing",
+                     Some("COMMIT")),
+                edge(Decided, Trigger::Message("DECIDE"), "observer, first for the instance", None),
+                view_change(),
+                crash(),
+            ],
+            PrePrepared => vec![
+                edge(Prepared, Trigger::Message("PREPARE"), "quorum for the node's value", Some("COMMIT")),
+                view_change(),
+                crash(),
+            ],
+            Prepared => vec![
+                edge(Committed, Trigger::Message("COMMIT"), "quorum for the node's value", Some("DECIDE")),
+                view_change(),
+                crash(),
+            ],
+            Committed => vec![
+                edge(Decided, Trigger::Message("DECIDE"), "for the node's value", Some("CHECKPOINT")),
+                view_change(),
+                crash(),
+            ],
+            ViewChanging => vec![
+                edge(PrePrepared,
+                     Trigger::Message("NEW-VIEW"),
+                     "from the new view's primary, for a value the node may prepare",
+                     Some("PREPARE")),
+                edge(Init,
+                     Trigger::Message("NEW-VIEW"),
+                     "from the new view's primary, for a value the node is locked against",
+                     None),
+                edge(Decided,
+                     Trigger::Message("DECIDE"),
+                     "for the node's value, with a COMMIT quorum for it",
+                     Some("CHECKPOINT")),
+                crash(),
+            ],
+            Decided => vec![
+                edge(Init, Trigger::Message("DECIDE"), "log mode, with another instance to come", None),
+                crash(),
+            ],
+            Failed => vec![],
+        }
+    }
+}
+
+/// View (round) number; the primary of view v leads that round
+pub type View = u64;
+
+/// Sequence number of a consensus instance; instances are decided one after another
+pub type Seq = u64;
+
+/// How far a node has got: its instance, then its view, then its phase in that
+/// view. A node following the protocol only ever moves forward in this order.
+pub type Progress = (Seq, View, NodeState);
+
+/// Sequence number of the first consensus instance
+pub const FIRST_SEQ: Seq = 1;
+
+/// Message types in the protocol. Normal-case messages are stamped with the
+/// consensus instance they belong to and the sender's view. As JSON each is an
+/// object keyed by its variant, e.g. {"Prepare": {"seq": 1, "view": 0, "value":
+/// "V1"}}, {"NewView": [1, "V1"]} or {"Checkpoint": 1}.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
-pub enum MessageType {
-    Propose(Value),
-    Prepare(Value),
-    Commit(Value),
-    Decide(Value),
+pub enum MessageType<V = Value> {
+    Propose { seq: Seq, view: View, value: V, proposer: NodeId },
+    PrePrepare { seq: Seq, view: View, value: V, proposer: NodeId },
+    Prepare { seq: Seq, view: View, value: V },
+    Commit { seq: Seq, view: View, value: V },
+    Decide { seq: Seq, view: View, value: V },
+    ViewChange(View),      // Vote to move to the given view
+    NewView(View, V),  // New primary starts the given view with a value
+    Checkpoint(Seq),       // Sender decided the instance with this sequence number
+}
+
+impl<V> MessageType<V> {
+    /// The view this message was sent in; checkpoints outlive views and have none
+    pub fn view(&self) -> Option<View> {
+        match self {
+            MessageType::Propose { view, .. }
+            | MessageType::PrePrepare { view, .. }
+            | MessageType::Prepare { view, .. }
+            | MessageType::Commit { view, .. }
+            | MessageType::Decide { view, .. }
+            | MessageType::ViewChange(view)
+            | MessageType::NewView(view, _) => Some(*view),
+            MessageType::Checkpoint(_) => None,
+        }
+    }
+
+    /// The consensus instance this message belongs to; view changes apply to
+    /// whichever instance the receiver is running and have none
+    pub fn seq(&self) -> Option<Seq> {
+        match self {
+            MessageType::Propose { seq, .. }
+            | MessageType::PrePrepare { seq, .. }
+            | MessageType::Prepare { seq, .. }
+            | MessageType::Commit { seq, .. }
+            | MessageType::Decide { seq, .. }
+            | MessageType::Checkpoint(seq) => Some(*seq),
+            MessageType::ViewChange(_) | MessageType::NewView(..) => None,
+        }
+    }
+
+    /// The value this message proposes or votes for, if it carries one
+    pub fn value(&self) -> Option<&V> {
+        match self {
+            MessageType::Propose { value, .. }
+            | MessageType::PrePrepare { value, .. }
+            | MessageType::Prepare { value, .. }
+            | MessageType::Commit { value, .. }
+            | MessageType::Decide { value, .. }
+            | MessageType::NewView(_, value) => Some(value),
+            MessageType::ViewChange(_) | MessageType::Checkpoint(_) => None,
+        }
+    }
+}
+
+/// The message's name in capitals, then its value and stamps, e.g.
+/// `PREPARE(V1, seq 1, view 0)`
+impl<V: Display> Display for MessageType<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageType::Propose { seq, view, value, proposer } => {
+                write!(f, "PROPOSE({}, seq {}, view {}, proposer {})", value, seq, view, proposer)
+            }
+            MessageType::PrePrepare { seq, view, value, proposer } => {
+                write!(f, "PRE-PREPARE({}, seq {}, view {}, proposer {})", value, seq, view, proposer)
+            }
+            MessageType::Prepare { seq, view, value } => write!(f, "PREPARE({}, seq {}, view {})", value, seq, view),
+            MessageType::Commit { seq, view, value } => write!(f, "COMMIT({}, seq {}, view {})", value, seq, view),
+            MessageType::Decide { seq, view, value } => write!(f, "DECIDE({}, seq {}, view {})", value, seq, view),
+            MessageType::ViewChange(view) => write!(f, "VIEW-CHANGE(view {})", view),
+            MessageType::NewView(view, value) => write!(f, "NEW-VIEW({}, view {})", value, view),
+            MessageType::Checkpoint(seq) => write!(f, "CHECKPOINT(seq {})", seq),
+        }
+    }
 }
 
 /// Timer types for non-deterministic actions
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
-pub enum ConsensusTimer {
-    ProposeValue(Value),
+pub enum ConsensusTimer<V = Value> {
+    ProposeValue(V),
+    ViewChange,  // Fires if the current primary seems stuck
+    Crash,       // Fires at an arbitrary point and stops a crash-faulty node
+    HealPartition,  // Fires at an arbitrary point and reconnects a partitioned node
+    Retry,       // Fires if the node seems stuck, and re-sends its messages in case they were lost
+}
+
+/// Fault assumption used to size quorums
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FaultModel {
+    /// Faulty nodes stop, but never lie
+    Crash,
+    /// Faulty nodes may behave arbitrarily
+    Byzantine,
+}
+
+/// How the nodes listed in `ConsensusActor::faulty_nodes` misbehave
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum FaultKind {
+    /// Follow the protocol until crashing at an arbitrary point, then stop
+    Crash,
+    /// Byzantine: send conflicting PREPARE/COMMIT votes to different peers
+    Equivocate,
+    /// Byzantine: process messages and update state as usual, but never send anything
+    Mute,
+    /// Byzantine: a faulty proposer proposes one value to even-indexed peers and
+    /// another to odd-indexed ones, and as the first primary pre-prepares and votes
+    /// for each half's value with that half; other faulty nodes equivocate
+    LyingLeader,
+}
+
+impl FaultKind {
+    /// Whether faulty nodes of this kind vote for conflicting values, so that no
+    /// single value they hold tells what they voted for
+    pub fn equivocates(self) -> bool {
+        matches!(self, FaultKind::Equivocate | FaultKind::LyingLeader)
+    }
+}
+
+/// Whether a node takes part in the protocol or only learns its outcome
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum NodeRole {
+    /// Proposes, votes and decides
+    #[default]
+    Participant,
+    /// A learner: never proposes or votes, and takes in only DECIDEs, recording
+    /// the decision
+    Observer,
+}
+
+/// What a run decides, and so which of the agreement properties apply
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum ConsensusMode {
+    /// One value is chosen, in a single consensus instance
+    SingleDecree,
+    /// A log of values is committed in order, one instance after another
+    Log,
+}
+
+/// A split of the network into `group` and everyone else. Until a node's
+/// `HealPartition` timer fires, it sends nothing across the split.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Partition {
+    pub group: BTreeSet<NodeId>,
 }
 
-/// Node internal state
+impl Partition {
+    /// Whether `a` and `b` are on different sides of the split
+    pub fn separates(&self, a: NodeId, b: NodeId) -> bool {
+        self.group.contains(&a) != self.group.contains(&b)
+    }
+}
+
+/// Coarse delivery delay of a message, from how long the link it travels takes
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, clap::ValueEnum)]
+pub enum DelayClass {
+    Fast,
+    Slow,
+}
+
+/// Message delays measured against the view-change timeout. Whatever `slow_nodes`
+/// send is `Slow`, everything else is `Fast`. A node's view-change timeout only
+/// fires once no message of `view_change_timeout`'s class or faster is in flight
+/// anywhere, i.e. the timeout outlasts any exchange of such messages, while a
+/// slower message may still be on its way.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Delays {
+    pub slow_nodes: BTreeSet<NodeId>,
+    pub view_change_timeout: DelayClass,
+}
+
+impl Delays {
+    /// The delay class of a message sent by `src`
+    pub fn class(&self, src: NodeId) -> DelayClass {
+        if self.slow_nodes.contains(&src) {
+            DelayClass::Slow
+        } else {
+            DelayClass::Fast
+        }
+    }
+
+    /// Whether a view-change timeout may fire with `in_flight` messages, given by
+    /// sender, still to be acted on. Pending view-change votes are no sign of
+    /// progress, so they never hold a timeout back.
+    pub fn may_time_out<'a>(&self, mut in_flight: impl Iterator<Item = (NodeId, &'a MessageType)>) -> bool {
+        in_flight.all(|(src, msg)| {
+            matches!(msg, MessageType::ViewChange(_)) || self.class(src) > self.view_change_timeout
+        })
+    }
+}
+
+/// Quorum size needed to tolerate `faulty_count` faults among `num_nodes` nodes.
+///
+/// Crash faults need a simple majority, floor(n/2) + 1. Byzantine faults need
+/// ceil((n + f + 1) / 2), which is the familiar 2f + 1 when n = 3f + 1 and keeps
+/// any two quorums overlapping in at least f + 1 nodes (so in one honest node).
+pub fn quorum_for(num_nodes: usize, faulty_count: usize, fault_model: FaultModel) -> usize {
+    match fault_model {
+        FaultModel::Crash => num_nodes / 2 + 1,
+        FaultModel::Byzantine => (num_nodes + faulty_count) / 2 + 1,
+    }
+}
+
+/// How many of `num_nodes` nodes may fail while the rest can still gather a quorum
+/// of `quorum_size`. Beyond that, a system can never decide.
+pub fn max_tolerable_faults(num_nodes: usize, quorum_size: usize) -> usize {
+    num_nodes.saturating_sub(quorum_size)
+}
+
+/// Whether a quorum of `quorum_size` among `num_nodes` nodes copes with `crash`
+/// crashed and `byzantine` equivocating nodes at once: the rest can still gather
+/// a quorum, and any two quorums share more than `byzantine` nodes, so an honest
+/// one. Even a quorum of n - c - b, the most the rest can gather, needs n > 3b + 2c.
+pub fn tolerates_faults(num_nodes: usize, quorum_size: usize, crash: usize, byzantine: usize) -> bool {
+    crash + byzantine <= max_tolerable_faults(num_nodes, quorum_size)
+        && (byzantine == 0 || 2 * quorum_size > num_nodes + byzantine)
+}
+
+/// Whether any two quorums of `quorum_size` among `num_nodes` nodes share a node.
+/// Without that overlap two disjoint groups can decide different values.
+pub fn quorums_intersect(num_nodes: usize, quorum_size: usize) -> bool {
+    2 * quorum_size > num_nodes
+}
+
+/// `quorums_intersect` for quorums weighed by stake: any two quorums of
+/// `quorum_weight` among nodes of the given `weights` share some stake
+pub fn weighted_quorums_intersect(weights: &[u64], quorum_weight: u64) -> bool {
+    2 * quorum_weight > weights.iter().sum()
+}
+
+/// `tolerates_faults` for quorums weighed by stake, taking the heaviest nodes to
+/// be the faulty ones: the rest still gather `quorum_weight`, and any two quorums
+/// share more stake than the `byzantine` heaviest nodes hold
+pub fn weighted_tolerates_faults(weights: &[u64], quorum_weight: u64, crash: usize, byzantine: usize) -> bool {
+    let mut heaviest = weights.to_vec();
+    heaviest.sort_unstable_by(|a, b| b.cmp(a));
+    let total: u64 = heaviest.iter().sum();
+    let faulty: u64 = heaviest.iter().take(crash + byzantine).sum();
+    let equivocating: u64 = heaviest.iter().take(byzantine).sum();
+    total - faulty >= quorum_weight && (byzantine == 0 || 2 * quorum_weight > total + equivocating)
+}
+
+/// How PREPAREs and COMMITs add up to a quorum. Weighed by stake, the distinct
+/// senders' weights must add up to `quorum_weight`, with nodes missing from
+/// `weights` weighing 1; otherwise each sender counts once and a quorum is the
+/// counting node's `quorum_size`. Held by the actor rather than by each node, as
+/// it is the same for all of them and never changes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stake {
+    pub weights: HashMap<NodeId, u64>,  // Stake of nodes weighing other than 1
+    pub quorum_weight: Option<u64>,  // Stake a quorum needs, if weighed by stake
+}
+
+impl Stake {
+    /// Stake of `node`
+    pub fn weight(&self, node: NodeId) -> u64 {
+        self.weights.get(&node).copied().unwrap_or(1)
+    }
+
+    /// Total stake of `senders`
+    pub fn weight_of(&self, senders: &BTreeSet<NodeId>) -> u64 {
+        senders.iter().map(|&node| self.weight(node)).sum()
+    }
+
+    /// Stake a node believing in a quorum of `quorum_size` needs for a quorum
+    pub fn threshold(&self, quorum_size: usize) -> u64 {
+        self.quorum_weight.unwrap_or(quorum_size as u64)
+    }
+}
+
+/// Maps keyed by something JSON can't use as an object key, such as `(Seq, V)`
+/// or a `Value::Custom`, serialized instead as a list of `[key, entry]` pairs in
+/// key order, so the output doesn't depend on hash order either
+mod map_entries {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    pub fn serialize<K: Ord + Serialize, T: Serialize, S: Serializer>(
+        map: &HashMap<K, T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        serializer.collect_seq(entries)
+    }
+
+    pub fn deserialize<'de, K, T, D>(deserializer: D) -> Result<HashMap<K, T>, D::Error>
+    where
+        K: Eq + Hash + Deserialize<'de>,
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, T)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+    }
+}
+
+/// Node internal state. As JSON, e.g. in `--replay` output, it is an object with
+/// one member per field: values are their variant names ("V1", "Bottom", or
+/// {"Custom": 4}), phases likewise ("PrePrepared"), and the maps keyed by
+/// instance and value (the counts, senders, quorum flags and `pending_prepares`)
+/// lists of `[key, entry]` pairs such as `[[1, "V1"], 2]`. Maps keyed by a view
+/// or an instance alone are objects with the number as a string key. Renaming a
+/// field or variant breaks traces saved before, and the tests pin this format.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct ConsensusNodeState {
+pub struct ConsensusNodeState<V: ConsensusValue = Value> {
     pub id: NodeId,
+    pub seq: Seq,  // Instance currently being decided
+    pub view: View,
     pub state: NodeState,
-    pub value: Option<Value>,
-    pub prepare_count: HashMap<Value, usize>,
-    pub commit_count: HashMap<Value, usize>,
-    pub decided: bool,
-    pub quorum_size: usize,
+    pub value: Option<V>,
+    pub proposer: Option<NodeId>,  // Node that proposed the accepted value
+    #[serde(with = "map_entries")]
+    pub prepare_count: HashMap<(Seq, V), usize>,
+    #[serde(with = "map_entries")]
+    pub commit_count: HashMap<(Seq, V), usize>,
+    #[serde(with = "map_entries")]
+    pub prepare_senders: HashMap<(Seq, V), BTreeSet<NodeId>>,  // Whose PREPAREs were counted
+    #[serde(with = "map_entries")]
+    pub commit_senders: HashMap<(Seq, V), BTreeSet<NodeId>>,   // Whose COMMITs were counted
+    #[serde(with = "map_entries")]
+    pub reached_prepare_quorum: HashMap<(Seq, V), bool>,  // Whether the PREPAREs counted ever made a quorum
+    #[serde(with = "map_entries")]
+    pub reached_commit_quorum: HashMap<(Seq, V), bool>,   // Likewise for COMMITs, outliving view changes
+    pub view_change_votes: HashMap<View, BTreeSet<NodeId>>,  // Who voted to move to each later view
+    pub checkpoint_votes: HashMap<Seq, BTreeSet<NodeId>>,    // Who reported a checkpoint of each instance
+    pub checkpoint: Option<Seq>,  // Latest stable checkpoint, once a quorum has reported it
+    pub decisions: BTreeMap<Seq, V>,  // Value decided for each finished instance
+    pub decided: bool,  // Whether the current instance is decided
+    pub quorum_size: usize,  // Votes needed for view changes and checkpoints, and unweighed PREPAREs and COMMITs
     pub is_faulty: bool,
+    pub partitioned: bool,  // Cut off from the other side of the partition, if any
     pub has_proposed: bool,  // Track if this node has proposed a value
+    pub furthest: Progress,  // Furthest progress reached so far; see `note_progress`
+    pub conflicting_proposal: bool,  // Whether a proposal for another value than ours arrived
+    pub changed_value: bool,  // Whether the accepted value was ever replaced; see `note_value`
+    pub locked_value: Option<V>,  // Value this node sent a COMMIT for, in this instance
+    pub locked_round: View,  // View in which it locked on `locked_value`
+    pub retry_count: u8,  // Retry timeouts handled so far, up to the actor's `max_retries`
+    /// Senders of PREPAREs for the current instance and view that arrived before this
+    /// node accepted a value, by the value they prepare; only kept by actors that
+    /// `buffer_prepares`, and counted once the value is accepted. Senders rather
+    /// than a count, so that a repeated PREPARE is still only counted once.
+    #[serde(with = "map_entries")]
+    pub pending_prepares: HashMap<V, BTreeSet<NodeId>>,
+    /// Messages and timeouts that changed this node so far, numbering the `timeline` entries
+    #[cfg(feature = "timeline")]
+    pub events: usize,
+    /// Every state and value this node moved to, with the change that moved it: the
+    /// node's own event count while checking, the trace's step once replayed. Not
+    /// hashed, so the state space is unchanged: states reached along different paths
+    /// are still one state, keeping the timeline of whichever path got there first.
+    #[cfg(feature = "timeline")]
+    pub timeline: Vec<(usize, NodeState, Option<V>)>,
 }
 
-impl ConsensusNodeState {
+impl<V: ConsensusValue> ConsensusNodeState<V> {
     pub fn new(id: NodeId, quorum_size: usize) -> Self {
         ConsensusNodeState {
             id,
+            seq: FIRST_SEQ,
+            view: 0,
             state: NodeState::Init,
             value: None,
+            proposer: None,
             prepare_count: HashMap::new(),
             commit_count: HashMap::new(),
+            prepare_senders: HashMap::new(),
+            commit_senders: HashMap::new(),
+            reached_prepare_quorum: HashMap::new(),
+            reached_commit_quorum: HashMap::new(),
+            view_change_votes: HashMap::new(),
+            checkpoint_votes: HashMap::new(),
+            checkpoint: None,
+            decisions: BTreeMap::new(),
             decided: false,
             quorum_size,
             is_faulty: false,
+            partitioned: false,
             has_proposed: false,
+            furthest: (FIRST_SEQ, 0, NodeState::Init),
+            conflicting_proposal: false,
+            changed_value: false,
+            locked_value: None,
+            locked_round: 0,
+            retry_count: 0,
+            pending_prepares: HashMap::new(),
+            #[cfg(feature = "timeline")]
+            events: 0,
+            #[cfg(feature = "timeline")]
+            timeline: vec![(0, NodeState::Init, None)],
+        }
+    }
+
+    /// A node that decided `value` for the first instance as the protocol would
+    /// have it: it accepted the value from node 0, counted PREPAREs and COMMITs
+    /// from a quorum of distinct senders (itself first, then the lowest IDs) and
+    /// is locked on it. Spares tests setting each field by hand, and getting them
+    /// subtly at odds with one another
+    #[cfg(test)]
+    pub fn decided(id: NodeId, quorum_size: usize, value: V) -> Self {
+        let mut state = ConsensusNodeState::new(id, quorum_size);
+        for sender in std::iter::once(id).chain((0..).filter(|&node| node != id)).take(quorum_size) {
+            state.record_prepare(&Stake::default(), FIRST_SEQ, &value, sender);
+            state.record_commit(&Stake::default(), FIRST_SEQ, &value, sender);
+        }
+        state.value = Some(value.clone());
+        state.proposer = Some(0);
+        state.locked_value = Some(value.clone());
+        state.state = NodeState::Decided;
+        state.decided = true;
+        state.decisions.insert(FIRST_SEQ, value);
+        state.furthest = state.progress();
+        state
+    }
+
+    pub fn progress(&self) -> Progress {
+        (self.seq, self.view, self.state.clone())
+    }
+
+    /// Raise `furthest` to the current progress. It is never lowered, so a node
+    /// that moved backward is left with `progress() < furthest`.
+    fn note_progress(state: &mut Cow<ConsensusNodeState<V>>) {
+        if state.progress() > state.furthest {
+            let progress = state.progress();
+            state.to_mut().furthest = progress;
+        }
+    }
+
+    /// The instance, view and accepted value, to hand to `note_value` afterwards
+    fn accepted(&self) -> (Seq, View, Option<V>) {
+        (self.seq, self.view, self.value.clone())
+    }
+
+    /// Flag `changed_value` if the value accepted `before` was replaced within the
+    /// same instance and view. Only a new view or instance may accept a new value.
+    fn note_value(state: &mut Cow<ConsensusNodeState<V>>, before: (Seq, View, Option<V>)) {
+        let (seq, view, value) = before;
+        if seq == state.seq && view == state.view && value.is_some() && value != state.value && !state.changed_value {
+            state.to_mut().changed_value = true;
+        }
+    }
+
+    /// Count the event just handled if it changed the node, adding it to the
+    /// timeline if it moved the node to another state or value. Events that changed
+    /// nothing stay uncounted, leaving them no-ops to the checker.
+    #[cfg(feature = "timeline")]
+    fn note_timeline(state: &mut Cow<ConsensusNodeState<V>>) {
+        let Cow::Owned(s) = state else { return };
+        s.events += 1;
+        let moved = s.timeline.last().is_none_or(|(_, last, value)| *last != s.state || *value != s.value);
+        if moved {
+            s.timeline.push((s.events, s.state.clone(), s.value.clone()));
+        }
+    }
+
+    /// Whether the locking discipline lets this node prepare `value`: a node that
+    /// sent a COMMIT in an earlier view prepares nothing else until it unlocks
+    pub fn may_prepare(&self, value: &V) -> bool {
+        self.locked_value.as_ref().is_none_or(|locked| locked == value)
+    }
+
+    /// Whether a quorum of PREPAREs for `value` in the current view, later than the
+    /// one this node locked in, releases its lock on another value
+    fn unlocks(&self, stake: &Stake, weight: u64) -> bool {
+        self.locked_value.is_some() && self.view > self.locked_round && self.has_quorum(stake, weight)
+    }
+
+    /// Whether senders of this much stake make a PREPARE or COMMIT quorum
+    pub fn has_quorum(&self, stake: &Stake, weight: u64) -> bool {
+        weight >= stake.threshold(self.quorum_size)
+    }
+
+    /// Stake of the nodes whose COMMITs for `value` in instance `seq` were counted
+    pub fn commit_weight(&self, stake: &Stake, seq: Seq, value: &V) -> u64 {
+        self.commit_senders.get(&(seq, value.clone())).map_or(0, |senders| stake.weight_of(senders))
+    }
+
+    /// Count `src`'s PREPARE for `value` in instance `seq`, returning the stake of
+    /// the senders counted so far, or None if that sender's PREPARE was already
+    /// counted. Noting when they make a quorum lets the model check no COMMIT is
+    /// counted before one.
+    pub fn record_prepare(&mut self, stake: &Stake, seq: Seq, value: &V, src: NodeId) -> Option<u64> {
+        let key = (seq, value.clone());
+        record_vote(&mut self.prepare_count, &mut self.prepare_senders, key.clone(), src)?;
+        let weight = stake.weight_of(&self.prepare_senders[&key]);
+        if self.has_quorum(stake, weight) {
+            self.reached_prepare_quorum.insert(key, true);
+        }
+        Some(weight)
+    }
+
+    /// Count `src`'s COMMIT for `value` in instance `seq`, as `record_prepare` does.
+    /// The quorum justifies the DECIDE sent on reaching it, so it is noted for
+    /// good, even once a view change drops the counts
+    pub fn record_commit(&mut self, stake: &Stake, seq: Seq, value: &V, src: NodeId) -> Option<u64> {
+        record_vote(&mut self.commit_count, &mut self.commit_senders, (seq, value.clone()), src)?;
+        let weight = self.commit_weight(stake, seq, value);
+        if self.has_quorum(stake, weight) {
+            self.reached_commit_quorum.insert((seq, value.clone()), true);
+        }
+        Some(weight)
+    }
+
+    /// PBFT garbage collection: once this node has decided an instance and a quorum
+    /// has checkpointed it, its vote counts for the decided value are dropped.
+    /// Checkpoints become stable in sequence order.
+    fn collect_garbage(&mut self) {
+        loop {
+            let seq = self.checkpoint.map_or(FIRST_SEQ, |c| c + 1);
+            let votes = self.checkpoint_votes.get(&seq).map_or(0, BTreeSet::len);
+            let Some(value) = self.decisions.get(&seq) else { return };
+            if votes < self.quorum_size {
+                return;
+            }
+            let key = (seq, value.clone());
+            self.prepare_count.remove(&key);
+            self.commit_count.remove(&key);
+            self.prepare_senders.remove(&key);
+            self.commit_senders.remove(&key);
+            self.reached_prepare_quorum.remove(&key);
+            self.reached_commit_quorum.remove(&key);
+            self.checkpoint = Some(seq);
         }
     }
 
-    pub fn has_quorum(&self, count: usize) -> bool {
-        count >= self.quorum_size
+    /// Start the next instance afresh, as if just constructed for it: back in
+    /// `Init` one instance on, in the same view, with nothing accepted, counted,
+    /// decided or proposed. Only who the node is and how it counts are kept (ID
+    /// and quorum size), along with whether it is faulty or partitioned. Unlike
+    /// `start_next_instance`, nothing is kept for checkpointing.
+    pub fn reset_for_new_instance(&mut self) {
+        let fresh = ConsensusNodeState::new(self.id, self.quorum_size);
+        *self = ConsensusNodeState {
+            seq: self.seq + 1,
+            view: self.view,
+            is_faulty: self.is_faulty,
+            partitioned: self.partitioned,
+            ..fresh
+        };
+    }
+
+    /// Move on to the next consensus instance, keeping the view and the vote
+    /// counts of earlier instances until they are checkpointed
+    fn start_next_instance(&mut self) {
+        self.seq += 1;
+        self.state = NodeState::Init;
+        self.value = None;
+        self.proposer = None;
+        self.decided = false;
+        self.has_proposed = false;
+        self.locked_value = None;
+        self.locked_round = 0;
+        self.pending_prepares.clear();
     }
 }
 
 // Manual Hash implementation since HashMap doesn't implement Hash
-impl Hash for ConsensusNodeState {
+impl<V: ConsensusValue> Hash for ConsensusNodeState<V> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.id.hash(state);
+        self.seq.hash(state);
+        self.view.hash(state);
         self.state.hash(state);
         self.value.hash(state);
+        self.proposer.hash(state);
+        sorted_counts(&self.prepare_count).hash(state);
+        sorted_counts(&self.commit_count).hash(state);
+        sorted_counts(&self.prepare_senders).hash(state);
+        sorted_counts(&self.commit_senders).hash(state);
+        sorted_counts(&self.reached_prepare_quorum).hash(state);
+        sorted_counts(&self.reached_commit_quorum).hash(state);
+        sorted_counts(&self.view_change_votes).hash(state);
+        sorted_counts(&self.checkpoint_votes).hash(state);
+        self.checkpoint.hash(state);
+        self.decisions.hash(state);
         self.decided.hash(state);
         self.quorum_size.hash(state);
         self.is_faulty.hash(state);
+        self.partitioned.hash(state);
         self.has_proposed.hash(state);
-        // Note: We skip prepare_count and commit_count since HashMap doesn't implement Hash
-        // This is acceptable for model checking as the other fields capture the essential state
+        self.furthest.hash(state);
+        self.conflicting_proposal.hash(state);
+        self.changed_value.hash(state);
+        self.locked_value.hash(state);
+        self.locked_round.hash(state);
+        self.retry_count.hash(state);
+        sorted_counts(&self.pending_prepares).hash(state);
+    }
+}
+
+// Symmetry reduction renames nodes; node IDs inside states and messages follow the renaming
+impl<V: ConsensusValue> Rewrite<Id> for ConsensusNodeState<V> {
+    fn rewrite<S>(&self, plan: &RewritePlan<Id, S>) -> Self {
+        ConsensusNodeState {
+            id: rewrite_node(self.id, plan),
+            proposer: self.proposer.map(|p| rewrite_node(p, plan)),
+            prepare_senders: rewrite_senders(&self.prepare_senders, plan),
+            commit_senders: rewrite_senders(&self.commit_senders, plan),
+            pending_prepares: rewrite_senders(&self.pending_prepares, plan),
+            view_change_votes: rewrite_senders(&self.view_change_votes, plan),
+            checkpoint_votes: rewrite_senders(&self.checkpoint_votes, plan),
+            ..self.clone()
+        }
+    }
+}
+
+impl<V: ConsensusValue> Rewrite<Id> for MessageType<V> {
+    fn rewrite<S>(&self, plan: &RewritePlan<Id, S>) -> Self {
+        match self.clone() {
+            MessageType::Propose { seq, view, value, proposer } => {
+                MessageType::Propose { seq, view, value, proposer: rewrite_node(proposer, plan) }
+            }
+            MessageType::PrePrepare { seq, view, value, proposer } => {
+                MessageType::PrePrepare { seq, view, value, proposer: rewrite_node(proposer, plan) }
+            }
+            msg => msg,
+        }
+    }
+}
+
+impl Rewrite<Id> for Value {
+    fn rewrite<S>(&self, _plan: &RewritePlan<Id, S>) -> Self {
+        self.clone()
+    }
+}
+
+fn rewrite_node<S>(node: NodeId, plan: &RewritePlan<Id, S>) -> NodeId {
+    usize::from(plan.rewrite(&Id::from(node)))
+}
+
+fn rewrite_senders<K: Clone + Eq + Hash, S>(
+    senders: &HashMap<K, BTreeSet<NodeId>>,
+    plan: &RewritePlan<Id, S>,
+) -> HashMap<K, BTreeSet<NodeId>> {
+    senders
+        .iter()
+        .map(|(key, nodes)| (key.clone(), nodes.iter().map(|&n| rewrite_node(n, plan)).collect()))
+        .collect()
+}
+
+/// Count a vote from `src` under `key` unless that sender was already counted
+fn record_vote<V: ConsensusValue>(
+    counts: &mut HashMap<(Seq, V), usize>,
+    senders: &mut HashMap<(Seq, V), BTreeSet<NodeId>>,
+    key: (Seq, V),
+    src: NodeId,
+) -> Option<usize> {
+    if !senders.entry(key.clone()).or_default().insert(src) {
+        return None;
     }
+    let count = counts.entry(key).or_insert(0);
+    *count += 1;
+    Some(*count)
+}
+
+/// Map entries in a deterministic order, since HashMap iteration order is not stable
+fn sorted_counts<K: Ord, V: Ord>(counts: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries: Vec<_> = counts.iter().collect();
+    entries.sort();
+    entries
 }
 
 /// Actor implementing consensus protocol
 #[derive(Clone)]
-pub struct ConsensusActor {
+pub struct ConsensusActor<V: ConsensusValue = Value> {
     pub peers: Vec<Id>,
     pub faulty_nodes: Vec<usize>,  // List of node IDs that should be faulty
+    pub fault_kind: FaultKind,     // How the faulty nodes misbehave
+    pub fault_kinds: HashMap<NodeId, FaultKind>,  // Faulty nodes misbehaving otherwise, as in mixed fault models
     pub quorum_size: usize,        // Quorum size for consensus
+    pub quorum_sizes: HashMap<NodeId, usize>,  // Nodes believing in another quorum size, as mid-reconfiguration
+    pub roles: HashMap<NodeId, NodeRole>,  // Nodes that are not participants
+    pub stake: Stake,              // How PREPAREs and COMMITs add up to a quorum
+    pub initial_proposals: Vec<V>,  // The values the proposer proposes at start-up
+    pub initial_values: HashMap<NodeId, V>,  // Each node's own input; if any, these nodes propose instead
+    pub equivocation: Option<(V, V)>,  // Values an equivocating node votes for with even and odd peers
+    pub allowed_values: Option<BTreeSet<V>>,  // The value domain; messages for other values are dropped
+    pub no_op: Option<V>,  // What a new primary with no value to carry over proposes, such as an abort
+    pub mode: ConsensusMode,       // A single decree, or a log of instances
+    pub num_instances: u64,        // How many consensus instances are decided in sequence
+    pub view_changes: bool,        // Whether nodes time out and elect a new primary
+    pub max_retries: u8,           // How often a node may time out and re-send its messages
+    pub buffer_prepares: bool,     // Whether PREPAREs arriving before their value are kept for later
+    pub proposer_id: NodeId,       // Node that proposes the value domain at start-up
+    pub partition: Option<Partition>,  // Split the network until each node heals
 }
 
 impl ConsensusActor {
-    /// Create a new consensus actor with no faulty nodes (used by tests)
-    #[allow(dead_code)]
+    /// Create a new consensus actor with no faulty nodes (used by tests). Unlike
+    /// `builder().build()`, this only refuses what no run could make sense of,
+    /// no peers or a quorum of none, by panicking; the rest is the caller's to size
     pub fn new(peers: Vec<Id>, quorum_size: usize) -> Self {
-        ConsensusActor {
-            peers,
-            faulty_nodes: Vec::new(),
-            quorum_size,
-        }
+        ConsensusActor::with_faults(peers, Vec::new(), quorum_size)
     }
 
-    /// Create a consensus actor with specified faulty nodes
+    /// Create a consensus actor with specified faulty nodes, as unchecked as `new`
     pub fn with_faults(peers: Vec<Id>, faulty_nodes: Vec<usize>, quorum_size: usize) -> Self {
-        ConsensusActor {
-            peers,
-            faulty_nodes,
-            quorum_size,
-        }
+        assert!(!peers.is_empty(), "a consensus actor needs at least one peer");
+        assert!(quorum_size > 0, "a quorum needs at least one vote");
+        ConsensusActor::builder().peers(peers).faulty(faulty_nodes).quorum(quorum_size).assemble()
+    }
+
+    /// Start configuring an actor by name rather than by argument position. The
+    /// proposer proposes V1 to V3, and equivocating nodes split V1 from V2
+    pub fn builder() -> ConsensusActorBuilder {
+        ConsensusActorBuilder::new().num_values(3).equivocation(Value::V1, Value::V2)
     }
 }
 
-impl Actor for ConsensusActor {
-    type Msg = MessageType;
-    type State = ConsensusNodeState;
-    type Timer = ConsensusTimer;
-    type Storage = ();
-    type Random = ();
+/// Fluent builder for `ConsensusActor`; `build` checks the configuration is consistent
+#[derive(Clone, Debug)]
+pub struct ConsensusActorBuilder<V: ConsensusValue = Value> {
+    peers: Vec<Id>,
+    faulty_nodes: Vec<usize>,
+    fault_kind: FaultKind,
+    fault_kinds: HashMap<NodeId, FaultKind>,
+    quorum_size: usize,
+    quorum_sizes: HashMap<NodeId, usize>,
+    roles: HashMap<NodeId, NodeRole>,
+    stake: Stake,
+    initial_proposals: Vec<V>,
+    initial_values: HashMap<NodeId, V>,
+    equivocation: Option<(V, V)>,
+    allowed_values: Option<BTreeSet<V>>,
+    no_op: Option<V>,
+    mode: ConsensusMode,
+    num_instances: u64,
+    view_changes: bool,
+    max_retries: u8,
+    buffer_prepares: bool,
+    proposer_id: Option<NodeId>,
+    partition: Option<Partition>,
+}
 
-    fn on_start(&self, id: Id, _storage: &Option<Self::Storage>, o: &mut Out<Self>) -> Self::State {
-        let node_id = usize::from(id);
-        // Use the configured quorum size
-        let mut state = ConsensusNodeState::new(node_id, self.quorum_size);
+impl<V: ConsensusValue> Default for ConsensusActorBuilder<V> {
+    fn default() -> Self {
+        ConsensusActorBuilder::new()
+    }
+}
 
-        // Check if this node should be faulty (per TLA+ NodeCrash action)
-        if self.faulty_nodes.contains(&node_id) {
-            state.is_faulty = true;
-            state.state = NodeState::Failed;
-            return state;  // Faulty nodes don't participate
-        }
+impl ConsensusActorBuilder {
+    /// Propose the first `num_values` values of the domain
+    pub fn num_values(mut self, num_values: usize) -> Self {
+        self.initial_proposals = Value::domain(num_values);
+        self
+    }
+}
 
-        // For non-deterministic model checking:
-        // Node 0 proposes all three possible values
-        // The model checker explores different orderings of message delivery
-        // creating branches where nodes might accept different values first
-        if node_id == 0 && !self.faulty_nodes.contains(&0) {
-            for &peer in &self.peers {
-                o.send(peer, MessageType::Propose(Value::V1));
-                o.send(peer, MessageType::Propose(Value::V2));
-                o.send(peer, MessageType::Propose(Value::V3));
-            }
+impl<V: ConsensusValue> ConsensusActorBuilder<V> {
+    /// Start configuring an actor over values of any type, with nothing to propose
+    /// until `initial_proposals` says what
+    pub fn new() -> Self {
+        ConsensusActorBuilder {
+            peers: Vec::new(),
+            faulty_nodes: Vec::new(),
+            fault_kind: FaultKind::Crash,
+            fault_kinds: HashMap::new(),
+            quorum_size: 0,
+            quorum_sizes: HashMap::new(),
+            roles: HashMap::new(),
+            stake: Stake::default(),
+            initial_proposals: Vec::new(),
+            initial_values: HashMap::new(),
+            equivocation: None,
+            allowed_values: None,
+            no_op: None,
+            mode: ConsensusMode::SingleDecree,
+            num_instances: 1,
+            view_changes: false,
+            max_retries: 0,
+            buffer_prepares: false,
+            proposer_id: None,
+            partition: None,
         }
+    }
 
-        state
+    pub fn peers(mut self, peers: Vec<Id>) -> Self {
+        self.peers = peers;
+        self
     }
 
-    fn on_msg(
-        &self,
-        _id: Id,
-        state: &mut Cow<Self::State>,
-        _src: Id,
-        msg: Self::Msg,
-        o: &mut Out<Self>,
-    ) {
-        // Don't process messages if node is faulty
-        if state.is_faulty {
-            return;
-        }
+    pub fn faulty(mut self, faulty_nodes: Vec<usize>) -> Self {
+        self.faulty_nodes = faulty_nodes;
+        self
+    }
 
-        match msg {
-            MessageType::Propose(value) => {
-                // ReceivePropose in TLA+: Node receives PROPOSE and broadcasts PREPARE
-                if state.state == NodeState::Init && state.value.is_none() {
-                    let mut new_state = state.as_ref().clone();
-                    new_state.value = Some(value.clone());
+    pub fn quorum(mut self, quorum_size: usize) -> Self {
+        self.quorum_size = quorum_size;
+        self
+    }
 
-                    // Broadcast PREPARE to ALL nodes (including self per TLA+ spec)
-                    for &peer in &self.peers {
-                        o.send(peer, MessageType::Prepare(value.clone()));
-                    }
+    /// Let some nodes believe in a quorum size other than `quorum`'s
+    pub fn quorum_sizes(mut self, quorum_sizes: HashMap<NodeId, usize>) -> Self {
+        self.quorum_sizes = quorum_sizes;
+        self
+    }
 
-                    // Initialize our own prepare count to 1 (counting our own PREPARE)
-                    *new_state.prepare_count.entry(value.clone()).or_insert(0) = 1;
+    /// Give some nodes a role other than participant
+    pub fn roles(mut self, roles: HashMap<NodeId, NodeRole>) -> Self {
+        self.roles = roles;
+        self
+    }
 
-                    *state = Cow::Owned(new_state);
-                }
-            }
+    /// Weigh PREPAREs and COMMITs by stake: a quorum is any set of senders whose
+    /// weights add up to `quorum_weight`, with nodes missing from `weights` weighing 1
+    pub fn weights(mut self, weights: HashMap<NodeId, u64>, quorum_weight: u64) -> Self {
+        self.stake = Stake { weights, quorum_weight: Some(quorum_weight) };
+        self
+    }
 
-            MessageType::Prepare(value) => {
-                // ReceivePrepare in TLA+: Count PREPARE messages for our accepted value
-                // Only process if we have accepted this value
-                if let Some(ref my_value) = state.value {
-                    if *my_value == value {
-                        let mut new_state = state.as_ref().clone();
-                        let count = new_state.prepare_count.entry(value.clone()).or_insert(0);
-                        *count += 1;
-                        let count_value = *count;
+    pub fn fault_kind(mut self, fault_kind: FaultKind) -> Self {
+        self.fault_kind = fault_kind;
+        self
+    }
 
-                        // If we reach quorum of PREPAREs and still in INIT, transition to PREPARED
-                        // Per TLA+: HasQuorum(prepareCount[n][m.value] + 1) - the +1 is already done above
-                        if new_state.has_quorum(count_value) && new_state.state == NodeState::Init {
-                            new_state.state = NodeState::Prepared;
+    /// Have some faulty nodes misbehave otherwise than `fault_kind`, e.g. one
+    /// crashing while another equivocates
+    pub fn fault_kinds(mut self, fault_kinds: HashMap<NodeId, FaultKind>) -> Self {
+        self.fault_kinds = fault_kinds;
+        self
+    }
 
-                            // Broadcast COMMIT to ALL nodes (including self)
-                            for &peer in &self.peers {
-                                o.send(peer, MessageType::Commit(value.clone()));
-                            }
+    /// Propose exactly these values, e.g. just `[V1]` for a quick happy-path run
+    pub fn initial_proposals(mut self, initial_proposals: Vec<V>) -> Self {
+        self.initial_proposals = initial_proposals;
+        self
+    }
 
-                            // Initialize our own commit count to 1 (counting our own COMMIT)
-                            *new_state.commit_count.entry(value.clone()).or_insert(0) = 1;
-                        }
+    /// Give nodes inputs of their own to propose, in place of the single proposer's
+    pub fn initial_values(mut self, initial_values: HashMap<NodeId, V>) -> Self {
+        self.initial_values = initial_values;
+        self
+    }
 
-                        *state = Cow::Owned(new_state);
-                    }
-                }
-            }
+    /// Have equivocating nodes vote for `even` with even-indexed peers and for
+    /// `odd` with the others
+    pub fn equivocation(mut self, even: V, odd: V) -> Self {
+        self.equivocation = Some((even, odd));
+        self
+    }
 
-            MessageType::Commit(value) => {
-                // ReceiveCommit in TLA+: Count COMMIT messages and transition when quorum reached
-                // Only process commits when in PREPARED state
-                if state.state == NodeState::Prepared {
-                    if let Some(ref my_value) = state.value {
-                        if *my_value == value {
-                            let mut new_state = state.as_ref().clone();
-                            let count = new_state.commit_count.entry(value.clone()).or_insert(0);
-                            *count += 1;
-                            let count_value = *count;
+    /// Make the value domain explicit: nodes drop any message for a value outside
+    /// `allowed_values` as malformed. Without one, every value is taken
+    pub fn allowed_values(mut self, allowed_values: BTreeSet<V>) -> Self {
+        self.allowed_values = Some(allowed_values);
+        self
+    }
 
-                            // If we reach quorum of COMMITs, transition to COMMITTED
-                            // Per TLA+: HasQuorum(commitCount[n][m.value] + 1)
-                            if new_state.has_quorum(count_value) {
-                                new_state.state = NodeState::Committed;
+    /// Have the primary of a new view that holds no value, neither locked nor
+    /// accepted, propose `no_op` rather than a fresh value: `Value::Bottom` lets a
+    /// view change abort the instance, as a two-phase commit's coordinator does
+    pub fn no_op(mut self, no_op: V) -> Self {
+        self.no_op = Some(no_op);
+        self
+    }
 
-                                // Broadcast DECIDE to ALL nodes (including self)
-                                for &peer in &self.peers {
-                                    o.send(peer, MessageType::Decide(value.clone()));
-                                }
-                            }
+    pub fn mode(mut self, mode: ConsensusMode) -> Self {
+        self.mode = mode;
+        self
+    }
 
-                            *state = Cow::Owned(new_state);
-                        }
-                    }
-                }
-            }
+    /// Decide `num_instances` instances in sequence; more than one needs log mode
+    pub fn instances(mut self, num_instances: u64) -> Self {
+        self.num_instances = num_instances;
+        self
+    }
+
+    pub fn view_changes(mut self, view_changes: bool) -> Self {
+        self.view_changes = view_changes;
+        self
+    }
+
+    /// Let every live node time out up to `max_retries` times without deciding,
+    /// re-sending its PROPOSEs and votes each time; 0 never retries
+    pub fn max_retries(mut self, max_retries: u8) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Let a node that is sent PREPAREs for a value before accepting any keep them,
+    /// and count them once it accepts that value, rather than leaving them in flight
+    pub fn buffer_prepares(mut self, buffer_prepares: bool) -> Self {
+        self.buffer_prepares = buffer_prepares;
+        self
+    }
+
+    /// Have this node propose at start-up rather than the first peer
+    pub fn proposer(mut self, proposer_id: NodeId) -> Self {
+        self.proposer_id = Some(proposer_id);
+        self
+    }
+
+    pub fn partition(mut self, partition: Option<Partition>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    /// Build the actor, rejecting an empty peer set, an empty quorum that every
+    /// node would reach alone, a quorum that no set of peers could ever reach
+    /// (for any node, or by any stake), faulty nodes or a proposer (the first peer
+    /// unless set) that aren't among the peers, nothing to propose, an empty run of
+    /// instances or several of them for a single decree, equivocating nodes with
+    /// nothing to equivocate between, observers that would have to take part: the
+    /// proposer, the first primary, faulty nodes, nodes with inputs, or any once
+    /// views change, honest proposals outside the allowed values, a lying leader
+    /// that isn't faulty, and fault kinds for nodes that aren't
+    pub fn build(self) -> Result<ConsensusActor<V>, String> {
+        if self.peers.is_empty() {
+            return Err("at least one peer is needed".to_string());
+        }
+        let proposer_id = self.proposer_id.unwrap_or_else(|| usize::from(self.peers[0]));
+        if self.quorum_size == 0 {
+            return Err("quorum size must be at least 1".to_string());
+        }
+        let mut faulty = BTreeSet::new();
+        for &node in &self.faulty_nodes {
+            if !self.peers.contains(&Id::from(node)) {
+                return Err(format!("faulty node {} is not one of the peers", node));
+            }
+            if !faulty.insert(node) {
+                return Err(format!("faulty node {} is listed twice", node));
+            }
+        }
+        if self.quorum_size > self.peers.len() {
+            return Err(format!(
+                "quorum size {} exceeds the {} peers",
+                self.quorum_size,
+                self.peers.len()
+            ));
+        }
+        for (&node, &quorum_size) in &self.quorum_sizes {
+            if !self.peers.contains(&Id::from(node)) {
+                return Err(format!("node {} with a quorum size of its own is not one of the peers", node));
+            }
+            if quorum_size == 0 {
+                return Err(format!("node {}'s quorum size must be at least 1", node));
+            }
+            if quorum_size > self.peers.len() {
+                return Err(format!(
+                    "node {}'s quorum size {} exceeds the {} peers",
+                    node,
+                    quorum_size,
+                    self.peers.len()
+                ));
+            }
+        }
+        if let Some(node) = self.stake.weights.keys().find(|&&node| !self.peers.contains(&Id::from(node))) {
+            return Err(format!("node {} with a weight is not one of the peers", node));
+        }
+        if let Some(quorum_weight) = self.stake.quorum_weight {
+            let total: u64 = self.peers.iter().map(|&peer| self.stake.weight(usize::from(peer))).sum();
+            if quorum_weight == 0 || quorum_weight > total {
+                return Err(format!("quorum weight {} is not between 1 and the total weight {}", quorum_weight, total));
+            }
+            if !self.quorum_sizes.is_empty() {
+                return Err("weighted quorums can't be combined with per-node quorum sizes".to_string());
+            }
+        }
+        if let Some(node) = self.initial_values.keys().find(|&&node| !self.peers.contains(&Id::from(node))) {
+            return Err(format!("node {} with an initial value is not one of the peers", node));
+        }
+        if !self.peers.contains(&Id::from(proposer_id)) {
+            return Err(format!("proposer {} is not one of the peers", proposer_id));
+        }
+        if self.initial_proposals.is_empty() {
+            return Err("the proposer needs at least one value to propose".to_string());
+        }
+        if let Some(allowed) = &self.allowed_values {
+            let mut proposed: Vec<&V> = if self.initial_values.is_empty() {
+                self.initial_proposals.iter().collect()
+            } else {
+                self.initial_values.values().collect()
+            };
+            proposed.extend(&self.no_op);
+            if let Some(value) = proposed.into_iter().find(|&value| !allowed.contains(value)) {
+                return Err(format!("proposed value {:?} is not one of the allowed values", value));
+            }
+        }
+        if let Some(node) = self.fault_kinds.keys().find(|node| !faulty.contains(node)) {
+            return Err(format!("node {} with a fault kind is not faulty", node));
+        }
+        let kind_of = |node: &NodeId| self.fault_kinds.get(node).copied().unwrap_or(self.fault_kind);
+        if faulty.iter().any(|node| kind_of(node).equivocates()) && self.equivocation.is_none() {
+            return Err("equivocating nodes need two values to vote for".to_string());
+        }
+        let lying = faulty.iter().any(|node| kind_of(node) == FaultKind::LyingLeader);
+        if lying && !(faulty.contains(&proposer_id) && kind_of(&proposer_id) == FaultKind::LyingLeader) {
+            return Err(format!("a lying leader needs the proposer {} to be faulty and lie", proposer_id));
+        }
+        for (&node, &role) in &self.roles {
+            if !self.peers.contains(&Id::from(node)) {
+                return Err(format!("node {} with a role is not one of the peers", node));
+            }
+            let takes_part = node == proposer_id
+                || Id::from(node) == self.peers[0]
+                || faulty.contains(&node)
+                || self.initial_values.contains_key(&node)
+                || self.view_changes;
+            if role == NodeRole::Observer && takes_part {
+                return Err(format!(
+                    "observer {} can't be the proposer, the first primary, faulty, given an input or take a \
+                     turn as primary",
+                    node
+                ));
+            }
+        }
+        if self.num_instances == 0 {
+            return Err("at least one consensus instance is needed".to_string());
+        }
+        if self.mode == ConsensusMode::SingleDecree && self.num_instances > 1 {
+            return Err(format!("a single decree decides one instance, not {}; use log mode", self.num_instances));
+        }
+        Ok(self.assemble())
+    }
+
+    /// The actor as configured, whether or not `build` would accept it
+    fn assemble(self) -> ConsensusActor<V> {
+        let proposer_id = self.proposer_id.or(self.peers.first().map(|&peer| usize::from(peer))).unwrap_or(0);
+        ConsensusActor {
+            peers: self.peers,
+            faulty_nodes: self.faulty_nodes,
+            fault_kind: self.fault_kind,
+            fault_kinds: self.fault_kinds,
+            quorum_size: self.quorum_size,
+            quorum_sizes: self.quorum_sizes,
+            roles: self.roles,
+            stake: self.stake,
+            initial_proposals: self.initial_proposals,
+            initial_values: self.initial_values,
+            equivocation: self.equivocation,
+            allowed_values: self.allowed_values,
+            no_op: self.no_op,
+            mode: self.mode,
+            num_instances: self.num_instances,
+            view_changes: self.view_changes,
+            max_retries: self.max_retries,
+            buffer_prepares: self.buffer_prepares,
+            proposer_id,
+            partition: self.partition,
+        }
+    }
+}
+
+impl<V: ConsensusValue> ConsensusActor<V> {
+    /// Byzantine behavior: on the primary's PRE-PREPARE, send PREPARE and COMMIT for
+    /// one `equivocation` value (V1 by default) to even-indexed peers and for the
+    /// other (V2) to odd-indexed peers
+    fn equivocate(
+        &self,
+        state: &mut Cow<ConsensusNodeState<V>>,
+        src: Id,
+        msg: MessageType<V>,
+        o: &mut Out<Self>,
+    ) {
+        if let MessageType::PrePrepare { seq, view, value, .. } = msg {
+            let from_primary = usize::from(src) == self.primary(view);
+            if seq == state.seq && view == state.view && from_primary && state.value.is_none() {
+                let mut new_state = state.as_ref().clone();
+                new_state.value = Some(value);
+
+                let (even, odd) = self.equivocation.as_ref().expect("equivocating actors are built with two values");
+                for (index, &peer) in self.peers.iter().enumerate() {
+                    let value = if index % 2 == 0 { even.clone() } else { odd.clone() };
+                    o.send(peer, MessageType::Prepare { seq, view, value: value.clone() });
+                    o.send(peer, MessageType::Commit { seq, view, value });
+                }
+
+                *state = Cow::Owned(new_state);
+            }
+        }
+    }
+
+    /// Byzantine start-up of a lying leader: split the peers as `equivocate` does,
+    /// and propose the even value to one half and the odd value to the other, for
+    /// every instance. As the first primary, also pre-prepare each half's value
+    /// with that half, and prepare and commit it there, so that each half sees a
+    /// primary agreeing with it
+    fn lie(&self, state: &ConsensusNodeState<V>, o: &mut Out<Self>) {
+        if state.id != self.proposer_id {
+            return;
+        }
+        let (even, odd) = self.equivocation.as_ref().expect("equivocating actors are built with two values");
+        let primary = state.id == self.primary(state.view);
+        for (index, &peer) in self.peers.iter().enumerate() {
+            if self.partitioned_from(state, peer) {
+                continue;
+            }
+            let value = if index % 2 == 0 { even } else { odd };
+            for seq in self.instances() {
+                let (view, proposer) = (state.view, state.id);
+                o.send(peer, MessageType::Propose { seq, view, value: value.clone(), proposer });
+                if primary {
+                    o.send(peer, MessageType::PrePrepare { seq, view, value: value.clone(), proposer });
+                    o.send(peer, MessageType::Prepare { seq, view, value: value.clone() });
+                    o.send(peer, MessageType::Commit { seq, view, value: value.clone() });
+                }
+            }
+        }
+    }
+
+    /// Whether `node` takes part in the protocol or just learns its outcome
+    pub fn role_of(&self, node: NodeId) -> NodeRole {
+        self.roles.get(&node).copied().unwrap_or_default()
+    }
+
+    pub fn is_observer(&self, node: NodeId) -> bool {
+        self.role_of(node) == NodeRole::Observer
+    }
+
+    /// How many of the peers take part, and so count towards quorums
+    pub fn participants(&self) -> usize {
+        self.peers.iter().filter(|&&peer| !self.is_observer(usize::from(peer))).count()
+    }
+
+    /// Observer handling of a message: a DECIDE for the next instance to learn is
+    /// recorded as this node's decision, and everything else is ignored. The first
+    /// DECIDE is trusted, as only a node that counted a COMMIT quorum sends one;
+    /// instances are learned in order, later ones waiting in flight.
+    fn learn(&self, state: &mut Cow<ConsensusNodeState<V>>, msg: MessageType<V>) {
+        let MessageType::Decide { seq, value, .. } = msg else { return };
+        if seq != state.seq || state.decided {
+            return;
+        }
+        let learned = state.to_mut();
+        learned.value = Some(value.clone());
+        learned.decisions.insert(seq, value);
+        learned.decided = true;
+        learned.state = NodeState::Decided;
+        if self.instances().contains(&(seq + 1)) {
+            learned.start_next_instance();
+        }
+    }
+
+    /// How `node` misbehaves, should it be faulty
+    pub fn fault_kind_of(&self, node: NodeId) -> FaultKind {
+        self.fault_kinds.get(&node).copied().unwrap_or(self.fault_kind)
+    }
+
+    /// The quorum size `node` believes in
+    pub fn quorum_size_of(&self, node: NodeId) -> usize {
+        self.quorum_sizes.get(&node).copied().unwrap_or(self.quorum_size)
+    }
+
+    /// The smallest quorum size any participant believes in. Two such quorums are
+    /// the likeliest to miss each other, and the fewest live nodes that can still
+    /// decide, so intersection and the fault budget are judged by it
+    pub fn smallest_quorum(&self) -> usize {
+        self.peers
+            .iter()
+            .map(|&peer| usize::from(peer))
+            .filter(|&node| !self.is_observer(node))
+            .map(|node| self.quorum_size_of(node))
+            .min()
+            .unwrap_or(self.quorum_size)
+    }
+
+    /// Sequence numbers of every instance this actor takes part in
+    pub fn instances(&self) -> std::ops::Range<Seq> {
+        FIRST_SEQ..FIRST_SEQ + self.num_instances
+    }
+
+    /// The primary of a view rotates through the peers: node `v mod n` leads view `v`
+    pub fn primary(&self, view: View) -> NodeId {
+        usize::from(self.peers[view as usize % self.peers.len()])
+    }
+
+    /// Move a pre-prepared node that counted a quorum of PREPAREs for its value to
+    /// PREPARED, broadcasting its COMMIT
+    fn enter_prepared(&self, id: Id, state: &mut ConsensusNodeState<V>, view: View, value: &V, o: &mut Out<Self>) {
+        let seq = state.seq;
+        state.state = NodeState::Prepared;
+
+        // Sending COMMIT locks the node on the value for later views
+        state.locked_value = Some(value.clone());
+        state.locked_round = view;
+
+        // Broadcast COMMIT to ALL nodes (including self)
+        for &peer in &self.peers {
+            o.send(peer, MessageType::Commit { seq, view, value: value.clone() });
+        }
+
+        // Count our own COMMIT now, as with PREPARE
+        state.record_commit(&self.stake, seq, value, usize::from(id));
+    }
+
+    /// Protocol handling of a message by a node that follows the protocol
+    fn handle_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<ConsensusNodeState<V>>,
+        src: Id,
+        msg: MessageType<V>,
+        o: &mut Out<Self>,
+    ) {
+        // View change messages are what move a node between views
+        match msg {
+            MessageType::ViewChange(new_view) => {
+                // Count votes for a later view, each sender's once; once a quorum
+                // agrees, that view's primary announces it with NEW-VIEW
+                let counted = state.view_change_votes.get(&new_view).is_some_and(|voters| voters.contains(&usize::from(src)));
+                if new_view > state.view && !state.decided && !counted {
+                    let mut new_state = state.as_ref().clone();
+                    let voters = new_state.view_change_votes.entry(new_view).or_default();
+                    voters.insert(usize::from(src));
+
+                    if voters.len() == new_state.quorum_size && self.primary(new_view) == usize::from(id) {
+                        // Re-propose the value we are locked on or had, otherwise give up
+                        // on the instance with the no-op if there is one, or start afresh
+                        let value = new_state
+                            .locked_value
+                            .clone()
+                            .or(new_state.value.clone())
+                            .or(self.no_op.clone())
+                            .or_else(|| self.fresh_proposal(usize::from(id)));
+                        if let Some(value) = value {
+                            for &peer in &self.peers {
+                                o.send(peer, MessageType::NewView(new_view, value.clone()));
+                            }
+                        }
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+                return;
+            }
+
+            MessageType::NewView(new_view, value) => {
+                // Enter the new view; NEW-VIEW doubles as that view's PRE-PREPARE
+                // A node locked on another value enters the view without preparing,
+                // unless a quorum of the others' PREPAREs unlocks it later
+                if new_view > state.view && usize::from(src) == self.primary(new_view) && !state.decided {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.view = new_view;
+                    let seq = new_state.seq;
+                    new_state.commit_count.retain(|(s, _), _| *s != seq);
+                    new_state.prepare_count.retain(|(s, _), _| *s != seq);
+                    new_state.commit_senders.retain(|(s, _), _| *s != seq);
+                    new_state.prepare_senders.retain(|(s, _), _| *s != seq);
+                    new_state.reached_prepare_quorum.retain(|(s, _), _| *s != seq);
+                    new_state.view_change_votes.retain(|&v, _| v > new_view);
+                    new_state.pending_prepares.clear();
+
+                    if new_state.may_prepare(&value) {
+                        new_state.value = Some(value.clone());
+                        new_state.proposer = Some(usize::from(src));
+                        new_state.state = NodeState::PrePrepared;
+                        new_state.record_prepare(&self.stake, seq, &value, usize::from(id));
+                        for &peer in &self.peers {
+                            o.send(peer, MessageType::Prepare { seq, view: new_view, value: value.clone() });
+                        }
+                    } else {
+                        new_state.value = None;
+                        new_state.proposer = None;
+                        new_state.state = NodeState::Init;
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+                return;
+            }
+
+            MessageType::Checkpoint(seq) => {
+                // Count checkpoints (our own included, when delivered), each sender's
+                // once, for instances later than the latest stable checkpoint
+                let counted = state.checkpoint_votes.get(&seq).is_some_and(|senders| senders.contains(&usize::from(src)));
+                if state.checkpoint < Some(seq) && !counted {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.checkpoint_votes.entry(seq).or_default().insert(usize::from(src));
+                    new_state.collect_garbage();
+                    *state = Cow::Owned(new_state);
+                }
+                return;
+            }
+
+            _ => {}
+        }
+
+        // Only messages from our current view count towards its quorums. Messages
+        // from other views are left undelivered (a no-op) rather than counted.
+        let Some(view) = msg.view() else { return };
+        if view != state.view {
+            return;
+        }
+
+        // Likewise only messages for the instance we are running count: votes for
+        // one instance must never complete another's quorum
+        let Some(seq) = msg.seq() else { return };
+        if seq != state.seq {
+            return;
+        }
+
+        // The instance's messages are stale once it is checkpointed
+        if state.checkpoint >= Some(seq) {
+            return;
+        }
+
+        match msg {
+            MessageType::Propose { value, proposer, .. } => {
+                // Only the view's primary orders proposals: it turns the first one it
+                // sees into a PRE-PREPARE for all nodes. Backups ignore proposals, and
+                // a proposal must come from the node it names as its proposer.
+                // A node that has already accepted another value just notes the conflict.
+                if state.value.as_ref().is_some_and(|v| *v != value) {
+                    if !state.conflicting_proposal {
+                        state.to_mut().conflicting_proposal = true;
+                    }
+                } else if usize::from(id) == self.primary(view)
+                    && usize::from(src) == proposer
+                    && state.state == NodeState::Init
+                    && !state.has_proposed
+                {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.has_proposed = true;
+                    new_state.proposer = Some(proposer);
+
+                    // Broadcast PRE-PREPARE to ALL nodes (including self)
+                    for &peer in &self.peers {
+                        o.send(peer, MessageType::PrePrepare { seq, view, value: value.clone(), proposer });
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            MessageType::PrePrepare { value, proposer, .. } => {
+                // Node accepts the primary's PRE-PREPARE and broadcasts PREPARE
+                if usize::from(src) == self.primary(view)
+                    && state.state == NodeState::Init
+                    && state.value.is_none()
+                    && state.may_prepare(&value)
+                {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.value = Some(value.clone());
+                    new_state.proposer = Some(proposer);
+                    new_state.state = NodeState::PrePrepared;
+
+                    // Broadcast PREPARE to ALL nodes (including self per TLA+ spec)
+                    for &peer in &self.peers {
+                        o.send(peer, MessageType::Prepare { seq, view, value: value.clone() });
+                    }
+
+                    // Count our own PREPARE now; its later delivery to ourselves is a repeat
+                    new_state.record_prepare(&self.stake, seq, &value, usize::from(id));
+
+                    // and any buffered PREPAREs that got here ahead of the PRE-PREPARE,
+                    // which may already make a quorum. Those for other values are of
+                    // no more use.
+                    if let Some(early) = std::mem::take(&mut new_state.pending_prepares).remove(&value) {
+                        for sender in early {
+                            new_state.record_prepare(&self.stake, seq, &value, sender);
+                        }
+                        let weight = self.stake.weight_of(&new_state.prepare_senders[&(seq, value.clone())]);
+                        if new_state.has_quorum(&self.stake, weight) {
+                            self.enter_prepared(id, &mut new_state, view, &value, o);
+                        }
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            MessageType::Prepare { value, .. } => {
+                // A node sitting out a view because of its lock counts the PREPAREs
+                // for the view's value, and a quorum of them unlocks it: the value is
+                // prepared after all, so the node joins in and locks on it instead
+                if state.value.is_none() && !state.may_prepare(&value) {
+                    let mut new_state = state.as_ref().clone();
+                    let Some(weight) = new_state.record_prepare(&self.stake, seq, &value, usize::from(src)) else {
+                        return;
+                    };
+                    if new_state.unlocks(&self.stake, weight) {
+                        new_state.value = Some(value.clone());
+                        new_state.proposer = Some(self.primary(view));
+                        new_state.state = NodeState::Prepared;
+                        new_state.locked_value = Some(value.clone());
+                        new_state.locked_round = view;
+                        new_state.record_prepare(&self.stake, seq, &value, usize::from(id));
+                        new_state.record_commit(&self.stake, seq, &value, usize::from(id));
+                        for &peer in &self.peers {
+                            o.send(peer, MessageType::Prepare { seq, view, value: value.clone() });
+                            o.send(peer, MessageType::Commit { seq, view, value: value.clone() });
+                        }
+                    }
+                    *state = Cow::Owned(new_state);
+                    return;
+                }
+
+                // A PREPARE that got here before the PRE-PREPARE is kept for when its
+                // value is accepted, if this actor buffers them; otherwise it is left
+                // in flight
+                if state.value.is_none() && self.buffer_prepares {
+                    let src = usize::from(src);
+                    if !state.pending_prepares.get(&value).is_some_and(|senders| senders.contains(&src)) {
+                        state.to_mut().pending_prepares.entry(value).or_default().insert(src);
+                    }
+                    return;
+                }
+
+                // ReceivePrepare in TLA+: Count PREPARE messages for our accepted value
+                // Only process if we have accepted this value
+                if let Some(ref my_value) = state.value {
+                    if *my_value == value {
+                        // A PREPARE from a sender already counted (a duplicate or our
+                        // own) is ignored
+                        let mut new_state = state.as_ref().clone();
+                        let Some(weight) = new_state.record_prepare(&self.stake, seq, &value, usize::from(src)) else {
+                            return;
+                        };
+
+                        // If we reach quorum of PREPAREs after the PRE-PREPARE, transition to PREPARED
+                        // Per TLA+: HasQuorum(prepareCount[n][m.value] + 1) - the +1 is already done above
+                        if new_state.has_quorum(&self.stake, weight) && new_state.state == NodeState::PrePrepared {
+                            self.enter_prepared(id, &mut new_state, view, &value, o);
+                        }
+
+                        *state = Cow::Owned(new_state);
+                    }
+                }
+            }
+
+            MessageType::Commit { value, .. } => {
+                // ReceiveCommit in TLA+: Count COMMIT messages and transition when quorum reached
+                // Only process commits when in PREPARED state
+                if state.state == NodeState::Prepared {
+                    if let Some(ref my_value) = state.value {
+                        if *my_value == value {
+                            let mut new_state = state.as_ref().clone();
+                            let Some(weight) = new_state.record_commit(&self.stake, seq, &value, usize::from(src)) else {
+                                return;
+                            };
+
+                            // If we reach quorum of COMMITs, transition to COMMITTED
+                            // Per TLA+: HasQuorum(commitCount[n][m.value] + 1)
+                            if new_state.has_quorum(&self.stake, weight) {
+                                new_state.state = NodeState::Committed;
+
+                                // Broadcast DECIDE to ALL nodes (including self)
+                                for &peer in &self.peers {
+                                    o.send(peer, MessageType::Decide { seq, view, value: value.clone() });
+                                }
+                            }
+
+                            *state = Cow::Owned(new_state);
+                        }
+                    }
+                }
+            }
 
-            MessageType::Decide(value) => {
+            MessageType::Decide { value, .. } => {
                 // ReceiveDecide in TLA+: Finalize decision for this value
-                // A non-faulty node receives DECIDE and transitions to DECIDED state
+                // A non-faulty node receives DECIDE and transitions to DECIDED state,
+                // but only once it has committed the value itself: a node that times
+                // out after committing still has its COMMIT quorum to show for it
+                let committed = match state.state {
+                    NodeState::Committed => true,
+                    NodeState::ViewChanging => {
+                        state.has_quorum(&self.stake, state.commit_weight(&self.stake, seq, &value))
+                    }
+                    _ => false,
+                };
                 if let Some(ref my_value) = state.value {
-                    if *my_value == value && !state.decided {
+                    if *my_value == value && !state.decided && committed {
                         let mut new_state = state.as_ref().clone();
                         new_state.decided = true;
                         new_state.state = NodeState::Decided;
+                        new_state.decisions.insert(seq, value);
+
+                        // Announce the decision so the instance can be checkpointed
+                        for &peer in &self.peers {
+                            o.send(peer, MessageType::Checkpoint(seq));
+                        }
+                        new_state.collect_garbage();
+
+                        // The last instance stays decided; earlier ones hand over to the next
+                        if self.instances().contains(&(seq + 1)) {
+                            new_state.start_next_instance();
+                        }
+
                         *state = Cow::Owned(new_state);
                     }
                 }
             }
+
+            // Handled above, before the view filter
+            MessageType::ViewChange(_) | MessageType::NewView(..) | MessageType::Checkpoint(_) => {}
+        }
+    }
+
+    /// The values `node_id` proposes for each instance: the proposer's whole domain,
+    /// or just the node's own input if nodes were given any
+    fn proposals(&self, node_id: NodeId) -> Vec<V> {
+        if self.initial_values.is_empty() {
+            if node_id == self.proposer_id { self.initial_proposals.clone() } else { Vec::new() }
+        } else {
+            self.initial_values.get(&node_id).cloned().into_iter().collect()
+        }
+    }
+
+    /// What a new primary holding no value proposes: its own proposal if it has one,
+    /// or else one that some node did propose, so the view can't decide a value
+    /// nobody put forward
+    fn fresh_proposal(&self, node_id: NodeId) -> Option<V> {
+        self.proposals(node_id)
+            .into_iter()
+            .next()
+            .or_else(|| self.proposals(self.proposer_id).into_iter().next())
+            .or_else(|| self.initial_values.values().min().cloned())
+    }
+
+    /// Protocol handling of a timer by a node that follows the protocol
+    fn handle_timeout(
+        &self,
+        id: Id,
+        state: &mut Cow<ConsensusNodeState<V>>,
+        timer: &ConsensusTimer<V>,
+        o: &mut Out<Self>,
+    ) {
+        match timer {
+            ConsensusTimer::ProposeValue(value) => {
+                // Only propose if:
+                // 1. We're still in Init state (haven't accepted a proposal yet)
+                // 2. We haven't already proposed
+                if state.state == NodeState::Init && !state.has_proposed {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.has_proposed = true;
+
+                    // Broadcast PROPOSE to ALL nodes (including self per TLA+ spec)
+                    for &peer in &self.peers {
+                        o.send(peer, MessageType::Propose {
+                            seq: state.seq,
+                            view: state.view,
+                            value: value.clone(),
+                            proposer: usize::from(id),
+                        });
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            ConsensusTimer::ViewChange => {
+                // No decision yet: suspect the primary and vote for the next view
+                if !state.decided && state.state != NodeState::ViewChanging {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.state = NodeState::ViewChanging;
+
+                    for &peer in &self.peers {
+                        o.send(peer, MessageType::ViewChange(state.view + 1));
+                    }
+
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            ConsensusTimer::Crash => {
+                // Crash wherever the protocol happens to be; nothing survives the crash
+                let mut new_state = state.as_ref().clone();
+                new_state.state = NodeState::Failed;
+                o.cancel_timer(ConsensusTimer::ViewChange);
+                o.cancel_timer(ConsensusTimer::HealPartition);
+                o.cancel_timer(ConsensusTimer::Retry);
+                *state = Cow::Owned(new_state);
+            }
+
+            ConsensusTimer::HealPartition => {
+                // Messages dropped at the partition stay lost; only later sends get through
+                let mut new_state = state.as_ref().clone();
+                new_state.partitioned = false;
+                *state = Cow::Owned(new_state);
+            }
+
+            ConsensusTimer::Retry => {
+                // Not decided yet: whatever this node sent for the instance may have
+                // been lost, so send it again. Receivers ignore what they have already
+                // counted, so a retry that wasn't needed does no harm
+                if state.decided || state.retry_count >= self.max_retries {
+                    return;
+                }
+                let (seq, view) = (state.seq, state.view);
+                let mut resent = Vec::new();
+                match (&state.state, &state.value) {
+                    (NodeState::Init, _) => {
+                        for value in self.proposals(usize::from(id)) {
+                            resent.push(MessageType::Propose { seq, view, value, proposer: usize::from(id) });
+                        }
+                    }
+                    (NodeState::PrePrepared, Some(value)) => {
+                        resent.push(MessageType::Prepare { seq, view, value: value.clone() });
+                    }
+                    (NodeState::Prepared, Some(value)) => {
+                        resent.push(MessageType::Prepare { seq, view, value: value.clone() });
+                        resent.push(MessageType::Commit { seq, view, value: value.clone() });
+                    }
+                    (NodeState::Committed, Some(value)) => {
+                        resent.push(MessageType::Prepare { seq, view, value: value.clone() });
+                        resent.push(MessageType::Commit { seq, view, value: value.clone() });
+                        resent.push(MessageType::Decide { seq, view, value: value.clone() });
+                    }
+                    _ => {}
+                }
+                // The primary pre-prepared the value its PREPARE is for
+                if let (Some(value), Some(proposer)) = (&state.value, state.proposer) {
+                    if usize::from(id) == self.primary(view) && state.state != NodeState::ViewChanging {
+                        resent.insert(0, MessageType::PrePrepare { seq, view, value: value.clone(), proposer });
+                    }
+                }
+                for &peer in &self.peers {
+                    for msg in &resent {
+                        o.send(peer, msg.clone());
+                    }
+                }
+
+                let mut new_state = state.as_ref().clone();
+                new_state.retry_count += 1;
+                if new_state.retry_count < self.max_retries {
+                    o.set_timer(ConsensusTimer::Retry, model_timeout());
+                }
+                *state = Cow::Owned(new_state);
+            }
+        }
+    }
+
+    /// Forward a handler's commands, dropping sends that a partition keeps from
+    /// reaching the other side
+    fn send_within_partition(&self, state: &ConsensusNodeState<V>, sent: Out<Self>, o: &mut Out<Self>) {
+        let mut kept: Out<Self> = sent
+            .into_iter()
+            .filter(|command| match command {
+                Command::Send(dst, _) => !self.partitioned_from(state, *dst),
+                _ => true,
+            })
+            .collect();
+        o.append(&mut kept);
+    }
+
+    /// Whether a partition currently stops messages from `state`'s node reaching `dst`
+    fn partitioned_from(&self, state: &ConsensusNodeState<V>, dst: Id) -> bool {
+        state.partitioned
+            && self.partition.as_ref().is_some_and(|p| p.separates(state.id, usize::from(dst)))
+    }
+
+    /// Mute behavior: keep the timer changes a handler made but drop its sends
+    fn suppress_sends(muted: Out<Self>, o: &mut Out<Self>) {
+        let mut kept: Out<Self> = muted
+            .into_iter()
+            .filter(|command| !matches!(command, Command::Send(..)))
+            .collect();
+        o.append(&mut kept);
+    }
+}
+
+impl<V: ConsensusValue> Actor for ConsensusActor<V> {
+    type Msg = MessageType<V>;
+    type State = ConsensusNodeState<V>;
+    type Timer = ConsensusTimer<V>;
+    type Storage = ();
+    type Random = ();
+
+    fn on_start(&self, id: Id, _storage: &Option<Self::Storage>, o: &mut Out<Self>) -> Self::State {
+        let node_id = usize::from(id);
+        // Use the configured quorum size
+        let quorum_size = self.quorum_size_of(node_id);
+        let mut state = ConsensusNodeState::new(node_id, quorum_size);
+
+        // Every node starts on its side of the partition and heals at an arbitrary point
+        if self.partition.is_some() {
+            state.partitioned = true;
+            o.set_timer(ConsensusTimer::HealPartition, model_timeout());
+        }
+
+        // Observers only listen for decisions
+        if self.is_observer(node_id) {
+            return state;
+        }
+
+        // Check if this node should be faulty (per TLA+ NodeCrash action)
+        if self.faulty_nodes.contains(&node_id) {
+            state.is_faulty = true;
+            match self.fault_kind_of(node_id) {
+                // Runs the protocol until the Crash timer fires; the checker may fire
+                // it at any point, so crashes are explored at every protocol phase
+                FaultKind::Crash => o.set_timer(ConsensusTimer::Crash, model_timeout()),
+                // Looks alive, but every send is suppressed
+                FaultKind::Mute => {}
+                // Byzantine nodes don't follow the protocol
+                FaultKind::Equivocate => return state,
+                FaultKind::LyingLeader => {
+                    self.lie(&state, o);
+                    return state;
+                }
+            }
+        }
+
+        // For non-deterministic model checking:
+        // The proposer proposes every configured initial value, and the
+        // primary pre-prepares whichever proposal it happens to receive first
+        // The model checker explores different orderings of message delivery
+        // creating branches where nodes might accept different values first.
+        // A crash-faulty proposer proposes before it crashes; a mute one can't.
+        // With several instances, each one gets its own proposals.
+        // Given initial values, every node with one proposes just that instead.
+        let muted = state.is_faulty && self.fault_kind_of(node_id) == FaultKind::Mute;
+        let proposals = self.proposals(node_id);
+        if !muted {
+            for &peer in self.peers.iter().filter(|&&peer| !self.partitioned_from(&state, peer)) {
+                for seq in self.instances() {
+                    for value in &proposals {
+                        let proposal = MessageType::Propose { seq, view: state.view, value: value.clone(), proposer: node_id };
+                        o.send(peer, proposal);
+                    }
+                }
+            }
+        }
+
+        // Any live node may suspect the primary and start a view change
+        if self.view_changes {
+            o.set_timer(ConsensusTimer::ViewChange, model_timeout());
+        }
+
+        // Or re-send its messages, in case the network lost them
+        if self.max_retries > 0 {
+            o.set_timer(ConsensusTimer::Retry, model_timeout());
+        }
+
+        state
+    }
+
+    fn on_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        // Crashed nodes do nothing at all; equivocating nodes answer the primary
+        // with conflicting votes; mute nodes update their state but stay silent.
+        // Crash-faulty nodes behave until they crash. Nobody's sends cross a partition.
+        if state.state == NodeState::Failed {
+            return;
+        }
+        // Out-of-domain values are dropped before any handler counts them
+        if self.rejects(&msg) {
+            return;
+        }
+        let accepted = state.accepted();
+        let mut sent = Out::new();
+        let fault_kind = self.fault_kind_of(usize::from(id));
+        if self.is_observer(usize::from(id)) {
+            self.learn(state, msg);
+        } else if state.is_faulty && fault_kind.equivocates() {
+            self.equivocate(state, src, msg, &mut sent);
+        } else if state.is_faulty && fault_kind == FaultKind::Mute {
+            let mut muted = Out::new();
+            self.handle_msg(id, state, src, msg, &mut muted);
+            Self::suppress_sends(muted, &mut sent);
+        } else {
+            self.handle_msg(id, state, src, msg, &mut sent);
+        }
+        ConsensusNodeState::note_progress(state);
+        ConsensusNodeState::note_value(state, accepted);
+        #[cfg(feature = "timeline")]
+        ConsensusNodeState::note_timeline(state);
+        self.send_within_partition(state, sent, o);
+    }
+
+    fn on_timeout(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        timer: &Self::Timer,
+        o: &mut Out<Self>,
+    ) {
+        // Crashed nodes don't process timers (equivocating nodes only set the one
+        // healing a partition), and mute nodes act on them without sending
+        if state.state == NodeState::Failed {
+            return;
+        }
+        let accepted = state.accepted();
+        let mut sent = Out::new();
+        if state.is_faulty && self.fault_kind_of(usize::from(id)) == FaultKind::Mute {
+            let mut muted = Out::new();
+            self.handle_timeout(id, state, timer, &mut muted);
+            Self::suppress_sends(muted, &mut sent);
+        } else {
+            self.handle_timeout(id, state, timer, &mut sent);
+        }
+        ConsensusNodeState::note_progress(state);
+        ConsensusNodeState::note_value(state, accepted);
+        #[cfg(feature = "timeline")]
+        ConsensusNodeState::note_timeline(state);
+        self.send_within_partition(state, sent, o);
+    }
+}
+
+impl<V: ConsensusValue> ConsensusActor<V> {
+    /// Whether `msg` is dropped as malformed, for carrying a value outside the
+    /// actor's `allowed_values`
+    pub fn rejects(&self, msg: &MessageType<V>) -> bool {
+        match (&self.allowed_values, msg.value()) {
+            (Some(allowed), Some(value)) => !allowed.contains(value),
+            _ => false,
+        }
+    }
+
+    /// Whether delivering `msg` from `src` to the node in `state` would change
+    /// nothing: no state update and nothing sent, as for a stale or repeated vote.
+    /// A node changing views ignores the old view, so it counts as ignoring only
+    /// what it would have ignored in every phase it could have left.
+    pub fn ignores(&self, state: &ConsensusNodeState<V>, src: NodeId, msg: &MessageType<V>) -> bool {
+        let phases = if state.state == NodeState::ViewChanging {
+            vec![NodeState::Init, NodeState::PrePrepared, NodeState::Prepared, NodeState::Committed]
+        } else {
+            vec![state.state.clone()]
+        };
+        phases.into_iter().all(|phase| {
+            // Raising `furthest` is bookkeeping rather than a reaction to the message
+            let mut before = ConsensusNodeState { state: phase, ..state.clone() };
+            before.furthest = before.furthest.clone().max(before.progress());
+            let mut after = Cow::Borrowed(&before);
+            let mut out = Out::new();
+            self.on_msg(Id::from(state.id), &mut after, Id::from(src), msg.clone(), &mut out);
+            matches!(after, Cow::Borrowed(_)) && out.is_empty()
+        })
+    }
+}
+
+/// One scripted event in a trace applied by `ConsensusActor::replay`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TraceStep<V = Value> {
+    /// `node` sends a proposal of `value` to the primary of its view
+    Propose { node: NodeId, value: V },
+    /// Deliver a PRE-PREPARE for `value` from `src` to `dst`
+    DeliverPrePrepare { src: NodeId, dst: NodeId, value: V },
+    /// Deliver a PREPARE for `value` from `src` to `dst`
+    DeliverPrepare { src: NodeId, dst: NodeId, value: V },
+    /// Deliver a COMMIT for `value` from `src` to `dst`
+    DeliverCommit { src: NodeId, dst: NodeId, value: V },
+    /// Deliver a DECIDE for `value` from `src` to `dst`
+    DeliverDecide { src: NodeId, dst: NodeId, value: V },
+    /// `node` crashes and takes no further part
+    Crash { node: NodeId },
+}
+
+impl<V: ConsensusValue> ConsensusActor<V> {
+    /// Start one node per peer, then apply `steps` in order using the actor's own
+    /// handlers and return the final node states. Messages the nodes send are not
+    /// delivered unless a later step delivers them, so the trace alone decides the
+    /// outcome. Messages are stamped with the sender's current instance and view.
+    /// Fails with the index of the first step naming a node that isn't a peer.
+    pub fn replay(&self, steps: &[TraceStep<V>]) -> Result<Vec<ConsensusNodeState<V>>, usize> {
+        let mut out = Out::new();
+        let mut states: Vec<ConsensusNodeState<V>> =
+            self.peers.iter().map(|&id| self.on_start(id, &None, &mut out)).collect();
+        for (i, step) in steps.iter().cloned().enumerate() {
+            // Steps name nodes by ID, and states are kept in peer order
+            let index = |node: NodeId| self.peers.iter().position(|&peer| usize::from(peer) == node).ok_or(i);
+            let mut out = Out::new();
+            let (src, dst, msg) = match step {
+                TraceStep::Propose { node, value } => {
+                    let (seq, view) = (states[index(node)?].seq, states[index(node)?].view);
+                    (node, self.primary(view), MessageType::Propose { seq, view, value, proposer: node })
+                }
+                TraceStep::DeliverPrePrepare { src, dst, value } => {
+                    let (seq, view) = (states[index(src)?].seq, states[index(src)?].view);
+                    (src, dst, MessageType::PrePrepare { seq, view, value, proposer: src })
+                }
+                TraceStep::DeliverPrepare { src, dst, value } => {
+                    let (seq, view) = (states[index(src)?].seq, states[index(src)?].view);
+                    (src, dst, MessageType::Prepare { seq, view, value })
+                }
+                TraceStep::DeliverCommit { src, dst, value } => {
+                    let (seq, view) = (states[index(src)?].seq, states[index(src)?].view);
+                    (src, dst, MessageType::Commit { seq, view, value })
+                }
+                TraceStep::DeliverDecide { src, dst, value } => {
+                    let (seq, view) = (states[index(src)?].seq, states[index(src)?].view);
+                    (src, dst, MessageType::Decide { seq, view, value })
+                }
+                TraceStep::Crash { node } => {
+                    let node_index = index(node)?;
+                    let mut state = Cow::Owned(states[node_index].clone());
+                    self.on_timeout(Id::from(node), &mut state, &ConsensusTimer::Crash, &mut out);
+                    states[node_index] = state.into_owned();
+                    states[node_index].is_faulty = true;
+                    continue;
+                }
+            };
+            let dst_index = index(dst)?;
+            let mut state = Cow::Owned(states[dst_index].clone());
+            self.on_msg(Id::from(dst), &mut state, Id::from(src), msg, &mut out);
+            states[dst_index] = state.into_owned();
+        }
+        Ok(states)
+    }
+}
+
+/// Direct (non-actor) encoding of the protocol's normal case, in the style of the
+/// TLA+ spec: a global state of all nodes plus the set of messages in transit.
+/// Up to `max_faults` nodes may crash at any point.
+#[derive(Clone)]
+pub struct ConsensusModel {
+    pub num_nodes: usize,
+    pub max_faults: usize,
+    pub num_values: usize,  // How many distinct values node 0 proposes
+    pub quorum_sizes: HashMap<NodeId, usize>,  // Nodes whose quorum size differs from the common one
+    pub lying_leader: bool,  // Whether node 0 is one of the faults, and pre-prepares V1 and V2 to alternate nodes
+}
+
+/// A message in transit: (src, dst, message)
+pub type InFlight = (NodeId, NodeId, MessageType);
+
+/// Global state of the `ConsensusModel`
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ConsensusSystemState {
+    pub nodes: Vec<ConsensusNodeState>,
+    pub messages: BTreeSet<InFlight>,
+}
+
+/// Steps the `ConsensusModel` can take
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ConsensusAction {
+    Deliver(InFlight),
+    Crash(NodeId),
+}
+
+impl ConsensusModel {
+    pub fn new(num_nodes: usize, max_faults: usize) -> Self {
+        ConsensusModel {
+            num_nodes,
+            max_faults,
+            num_values: 3,
+            quorum_sizes: HashMap::new(),
+            lying_leader: false,
+        }
+    }
+
+    /// Broadcast a message from `src` to every node (including itself)
+    fn broadcast(&self, messages: &mut BTreeSet<InFlight>, src: NodeId, msg: MessageType) {
+        for dst in 0..self.num_nodes {
+            messages.insert((src, dst, msg.clone()));
+        }
+    }
+
+    /// Apply a delivered message to its recipient. Returns false if the recipient
+    /// ignores it, in which case the action is disabled.
+    fn deliver(&self, state: &mut ConsensusSystemState, (src, dst, msg): InFlight) -> bool {
+        let node = &mut state.nodes[dst];
+        if node.is_faulty {
+            return false;
+        }
+        let mut outgoing = Vec::new();
+
+        match msg {
+            // Primary (node 0) pre-prepares the first proposal it receives
+            MessageType::Propose { seq, view, value, proposer } => {
+                if dst != 0 || src != proposer || node.state != NodeState::Init || node.has_proposed {
+                    return false;
+                }
+                node.has_proposed = true;
+                node.proposer = Some(proposer);
+                outgoing.push(MessageType::PrePrepare { seq, view, value, proposer });
+            }
+            MessageType::PrePrepare { seq, view, value, proposer } => {
+                if src != 0 || node.state != NodeState::Init || node.value.is_some() {
+                    return false;
+                }
+                node.value = Some(value.clone());
+                node.proposer = Some(proposer);
+                node.state = NodeState::PrePrepared;
+                outgoing.push(MessageType::Prepare { seq, view, value });
+            }
+            // Counts start at zero; a node's own vote is counted when it is delivered
+            MessageType::Prepare { seq, view, value } => {
+                if node.value.as_ref() != Some(&value) {
+                    return false;
+                }
+                let Some(count) = node.record_prepare(&Stake::default(), seq, &value, src) else { return false };
+                if node.has_quorum(&Stake::default(), count) && node.state == NodeState::PrePrepared {
+                    node.state = NodeState::Prepared;
+                    outgoing.push(MessageType::Commit { seq, view, value });
+                }
+            }
+            MessageType::Commit { seq, view, value } => {
+                if node.state != NodeState::Prepared || node.value.as_ref() != Some(&value) {
+                    return false;
+                }
+                let Some(count) = node.record_commit(&Stake::default(), seq, &value, src) else { return false };
+                if node.has_quorum(&Stake::default(), count) {
+                    node.state = NodeState::Committed;
+                    outgoing.push(MessageType::Decide { seq, view, value });
+                }
+            }
+            // As in ConsensusActor, only a node that has committed the value itself
+            // may decide it
+            MessageType::Decide { seq, value, .. } => {
+                if node.state != NodeState::Committed || node.value.as_ref() != Some(&value) {
+                    return false;
+                }
+                node.decisions.insert(seq, value);
+                node.decided = true;
+                node.state = NodeState::Decided;
+            }
+            // A single view and instance: view changes, checkpoints and later instances
+            // are only modeled by ConsensusActor
+            MessageType::ViewChange(_) | MessageType::NewView(..) | MessageType::Checkpoint(_) => return false,
+        }
+
+        for msg in outgoing {
+            self.broadcast(&mut state.messages, dst, msg);
+        }
+        true
+    }
+
+    /// Check safety property: Agreement
+    pub fn check_agreement(&self, history: &[ConsensusNodeState]) -> bool {
+        let decided_values: Vec<_> = history
+            .iter()
+            .filter(|s| s.decided && !s.is_faulty)
+            .filter_map(|s| s.value.as_ref())
+            .collect();
+
+        // All non-faulty decided nodes must have the same value
+        if decided_values.len() > 1 {
+            let first = decided_values[0];
+            decided_values.iter().all(|&v| v == first)
+        } else {
+            true
+        }
+    }
+
+    /// Check safety property: No premature decision
+    pub fn check_no_premature_decision(&self, state: &ConsensusNodeState) -> bool {
+        if state.state == NodeState::Decided {
+            if let Some(ref value) = state.value {
+                let commit_count = state.commit_count.get(&(state.seq, value.clone())).unwrap_or(&0);
+                *commit_count >= state.quorum_size
+            } else {
+                false
+            }
+        } else {
+            true
+        }
+    }
+
+    /// Every value some honest node decides in some reachable state, found by
+    /// checking the model exhaustively (up to `max_faults` crashes)
+    pub fn reachable_decisions(&self) -> BTreeSet<Value> {
+        let decided = Arc::new(Mutex::new(BTreeSet::new()));
+        let visitor = {
+            let decided = Arc::clone(&decided);
+            move |path: Path<ConsensusSystemState, ConsensusAction>| {
+                let values = path.last_state().nodes.iter().filter(|n| !n.is_faulty).flat_map(|n| n.decisions.values());
+                decided.lock().unwrap().extend(values.cloned());
+            }
+        };
+        self.clone().checker().visitor(visitor).spawn_bfs().join();
+        let decided = decided.lock().unwrap();
+        decided.clone()
+    }
+}
+
+impl Model for ConsensusModel {
+    type State = ConsensusSystemState;
+    type Action = ConsensusAction;
+
+    fn init_states(&self) -> Vec<Self::State> {
+        let quorum_size = quorum_for(self.num_nodes, self.max_faults, FaultModel::Byzantine);
+        let mut state = ConsensusSystemState {
+            nodes: (0..self.num_nodes)
+                .map(|id| ConsensusNodeState::new(id, self.quorum_sizes.get(&id).copied().unwrap_or(quorum_size)))
+                .collect(),
+            messages: BTreeSet::new(),
+        };
+        if self.lying_leader {
+            // As ConsensusActor::lie does, pre-prepare, prepare and commit V1 with even
+            // nodes and V2 with odd ones, then take no further part
+            state.nodes[0].is_faulty = true;
+            for dst in 0..self.num_nodes {
+                let value = if dst % 2 == 0 { Value::V1 } else { Value::V2 };
+                let (seq, view) = (FIRST_SEQ, 0);
+                state.messages.insert((0, dst, MessageType::PrePrepare { seq, view, value: value.clone(), proposer: 0 }));
+                state.messages.insert((0, dst, MessageType::Prepare { seq, view, value: value.clone() }));
+                state.messages.insert((0, dst, MessageType::Commit { seq, view, value }));
+            }
+            return vec![state];
+        }
+        // Node 0 proposes every value in the domain, as the default proposer does in
+        // ConsensusActor::on_start
+        for value in Value::domain(self.num_values) {
+            self.broadcast(&mut state.messages, 0, MessageType::Propose { seq: FIRST_SEQ, view: 0, value, proposer: 0 });
+        }
+        vec![state]
+    }
+
+    fn actions(&self, state: &Self::State, actions: &mut Vec<Self::Action>) {
+        for message in &state.messages {
+            actions.push(ConsensusAction::Deliver(message.clone()));
+        }
+
+        // NodeCrash in TLA+: any live node may fail while fewer than max_faults have
+        let crashed = state.nodes.iter().filter(|n| n.is_faulty).count();
+        if crashed < self.max_faults {
+            for node in state.nodes.iter().filter(|n| !n.is_faulty) {
+                actions.push(ConsensusAction::Crash(node.id));
+            }
+        }
+    }
+
+    fn next_state(&self, last_state: &Self::State, action: Self::Action) -> Option<Self::State> {
+        let mut state = last_state.clone();
+        match action {
+            ConsensusAction::Deliver(message) => {
+                state.messages.remove(&message);
+                if !self.deliver(&mut state, message) {
+                    return None;
+                }
+            }
+            ConsensusAction::Crash(id) => {
+                state.nodes[id].is_faulty = true;
+                state.nodes[id].state = NodeState::Failed;
+            }
+        }
+        Some(state)
+    }
+
+    fn properties(&self) -> Vec<Property<Self>> {
+        vec![
+            Property::<Self>::always("agreement", |model, state| model.check_agreement(&state.nodes)),
+            Property::<Self>::always("no premature decision", |model, state| {
+                state.nodes.iter().all(|n| model.check_no_premature_decision(n))
+            }),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_initial_state() {
+        let state: ConsensusNodeState = ConsensusNodeState::new(0, 3);
+        assert_eq!(state.state, NodeState::Init);
+        assert_eq!(state.value, None);
+        assert!(!state.decided);
+    }
+
+    #[test]
+    fn test_transitions() {
+        let mut sorted = NodeState::ALL.to_vec();
+        sorted.sort();
+        assert_eq!(sorted, NodeState::ALL);
+
+        // Every phase but INIT is entered somehow, and each one can still decide or crash
+        let transitions: Vec<Transition> = NodeState::ALL.iter().flat_map(NodeState::transitions).collect();
+        for state in &NodeState::ALL[1..] {
+            assert!(transitions.iter().any(|t| t.to == *state), "nothing enters {}", state);
+        }
+        assert!(NodeState::Failed.transitions().is_empty());
+        for state in &NodeState::ALL[..6] {
+            assert!(state.transitions().iter().any(|t| t.to == NodeState::Failed));
+            assert!(state.transitions().iter().all(|t| t.from == *state));
+        }
+        let into_commit = NodeState::Prepared.transitions();
+        assert_eq!(into_commit[0].to, NodeState::Committed);
+        assert_eq!(into_commit[0].trigger, Trigger::Message("COMMIT"));
+        assert_eq!(into_commit[0].sends, Some("DECIDE"));
+    }
+
+    #[test]
+    fn test_quorum_logic() {
+        let state: ConsensusNodeState = ConsensusNodeState::new(0, 3);
+        let stake = Stake::default();
+        assert!(!state.has_quorum(&stake, 2));
+        assert!(state.has_quorum(&stake, 3));
+        assert!(state.has_quorum(&stake, 4));
+    }
+
+    #[cfg(feature = "timeline")]
+    #[test]
+    fn test_timeline() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        let peers: Vec<Id> = (0..2).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+        let mut node = actor.on_start(peers[1], &None, &mut Out::new());
+        let messages = [
+            MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 },
+            MessageType::Prepare { seq: 1, view: 0, value: Value::V2 },
+            MessageType::Prepare { seq: 1, view: 0, value: Value::V1 },
+        ];
+        for msg in messages {
+            let mut state = Cow::Borrowed(&node);
+            actor.on_msg(peers[1], &mut state, peers[0], msg, &mut Out::new());
+            node = state.into_owned();
+        }
+
+        // The PREPARE for another value changes nothing and isn't counted
+        assert_eq!(node.events, 2);
+        assert_eq!(
+            node.timeline,
+            vec![
+                (0, NodeState::Init, None),
+                (1, NodeState::PrePrepared, Some(Value::V1)),
+                (2, NodeState::Prepared, Some(Value::V1)),
+            ]
+        );
+
+        // The timeline is left out of the hash
+        let hash_of = |state: &ConsensusNodeState| {
+            let mut hasher = DefaultHasher::new();
+            state.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut rewritten = node.clone();
+        rewritten.timeline.clear();
+        rewritten.events = 0;
+        assert_eq!(hash_of(&rewritten), hash_of(&node));
+    }
+
+    #[test]
+    fn test_hash_includes_counts() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::Hasher;
+
+        fn hash_of(state: &ConsensusNodeState) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            state.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut state1 = ConsensusNodeState::new(0, 3);
+        state1.prepare_count.insert((1, Value::V1), 2);
+
+        let mut state2 = ConsensusNodeState::new(0, 3);
+        state2.prepare_count.insert((1, Value::V1), 4);
+
+        assert_ne!(hash_of(&state1), hash_of(&state2));
+
+        // Insertion order must not matter
+        state1.commit_count.insert((1, Value::V1), 1);
+        state1.commit_count.insert((1, Value::V2), 1);
+        state2.prepare_count.insert((1, Value::V1), 2);
+        state2.commit_count.insert((1, Value::V2), 1);
+        state2.commit_count.insert((1, Value::V1), 1);
+        assert_eq!(hash_of(&state1), hash_of(&state2));
+    }
+
+    #[test]
+    fn test_value_domain() {
+        assert!(Value::domain(0).is_empty());
+        assert_eq!(Value::domain(1), vec![Value::V1]);
+        assert_eq!(
+            Value::domain(5),
+            vec![Value::V1, Value::V2, Value::V3, Value::Custom(4), Value::Custom(5)]
+        );
+    }
+
+    #[test]
+    fn test_pre_prepare_phase() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+
+        // A backup ignores proposals; only the primary may order them
+        let mut backup = Cow::Owned(ConsensusNodeState::new(1, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut backup, peers[0], MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(backup.state, NodeState::Init);
+
+        // The primary turns its first proposal into a PRE-PREPARE and ignores later ones
+        let mut primary = Cow::Owned(ConsensusNodeState::new(0, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut primary, peers[0], MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        actor.on_msg(peers[0], &mut primary, peers[0], MessageType::Propose { seq: 1, view: 0, value: Value::V2, proposer: 0 }, &mut out);
+        assert_eq!(out.len(), peers.len());
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 })
+        )));
+
+        // A backup accepts the primary's PRE-PREPARE and broadcasts PREPARE
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut backup, peers[0], MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        assert_eq!(backup.state, NodeState::PrePrepared);
+        assert_eq!(backup.value, Some(Value::V1));
+        assert_eq!(backup.proposer, Some(0));
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::Prepare { seq: 1, view: 0, value: Value::V1 })
+        )));
+
+        // PRE-PREPARE from anyone but the primary is ignored
+        let mut other = Cow::Owned(ConsensusNodeState::new(2, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[2], &mut other, peers[1], MessageType::PrePrepare { seq: 1, view: 0, value: Value::V2, proposer: 0 }, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(other.value, None);
+
+        // A proposal forwarded on behalf of another node is not pre-prepared
+        let mut primary = Cow::Owned(ConsensusNodeState::new(0, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut primary, peers[1], MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        assert!(out.is_empty());
+        assert!(!primary.has_proposed);
+    }
+
+    #[test]
+    fn test_other_view_messages_ignored() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+
+        let mut state = ConsensusNodeState::new(1, 2);
+        state.view = 1;
+        state.value = Some(Value::V1);
+        state.state = NodeState::PrePrepared;
+        state.record_prepare(&Stake::default(), 1, &Value::V1, 1);
+        let mut state = Cow::Owned(state);
+
+        // A PREPARE from view 0 must not count towards the view 1 quorum
+        let mut out = Out::new();
+        let stale = MessageType::Prepare { seq: 1, view: 0, value: Value::V1 };
+        actor.on_msg(peers[1], &mut state, peers[2], stale, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(state.prepare_count[&(1, Value::V1)], 1);
+        assert_eq!(state.state, NodeState::PrePrepared);
+
+        // The same PREPARE in the current view does
+        let current = MessageType::Prepare { seq: 1, view: 1, value: Value::V1 };
+        actor.on_msg(peers[1], &mut state, peers[2], current, &mut out);
+        assert_eq!(state.prepare_count[&(1, Value::V1)], 2);
+        assert_eq!(state.state, NodeState::Prepared);
+    }
+
+    #[test]
+    fn test_duplicate_votes_ignored() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 3);
+
+        // Pre-preparing counts the node's own PREPARE, so its delivery back is a repeat
+        let mut state: Cow<ConsensusNodeState> = Cow::Owned(ConsensusNodeState::new(1, 3));
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut state, peers[0], MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut state, peers[1], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(state.prepare_count[&(1, Value::V1)], 1);
+
+        // The same PREPARE delivered twice is counted once and can't complete a quorum
+        let prepare = MessageType::Prepare { seq: 1, view: 0, value: Value::V1 };
+        actor.on_msg(peers[1], &mut state, peers[2], prepare.clone(), &mut out);
+        actor.on_msg(peers[1], &mut state, peers[2], prepare, &mut out);
+        assert_eq!(state.prepare_count[&(1, Value::V1)], 2);
+        assert_eq!(state.prepare_senders[&(1, Value::V1)], BTreeSet::from([1, 2]));
+        assert_eq!(state.state, NodeState::PrePrepared);
+        assert!(out.is_empty());
+
+        // A third sender does
+        actor.on_msg(peers[1], &mut state, peers[0], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(state.state, NodeState::Prepared);
+        assert_eq!(state.commit_count[&(1, Value::V1)], 1);
+
+        // So do repeated VIEW-CHANGE and CHECKPOINT votes, which leave the state untouched
+        let mut state: Cow<ConsensusNodeState> = Cow::Owned(ConsensusNodeState::new(1, 3));
+        for msg in [MessageType::ViewChange(1), MessageType::Checkpoint(1)] {
+            actor.on_msg(peers[1], &mut state, peers[2], msg.clone(), &mut out);
+            let counted = state.clone().into_owned();
+            let mut repeat = Cow::Borrowed(&counted);
+            actor.on_msg(peers[1], &mut repeat, peers[2], msg, &mut out);
+            assert!(matches!(repeat, Cow::Borrowed(_)));
+        }
+        assert_eq!(state.view_change_votes[&1], BTreeSet::from([2]));
+        assert_eq!(state.checkpoint_votes[&1], BTreeSet::from([2]));
+    }
+
+    #[test]
+    fn test_view_change() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor {
+            view_changes: true,
+            ..ConsensusActor::new(peers.clone(), 2)
+        };
+        assert_eq!(actor.primary(0), 0);
+        assert_eq!(actor.primary(1), 1);
+        assert_eq!(actor.primary(3), 0);
+
+        // Undecided nodes time out and vote for view 1
+        let mut node1 = Cow::Owned(ConsensusNodeState::new(1, 2));
+        let mut out = Out::new();
+        actor.on_timeout(peers[1], &mut node1, &ConsensusTimer::ViewChange, &mut out);
+        assert_eq!(node1.state, NodeState::ViewChanging);
+        assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::ViewChange(1)))));
+
+        // Node 1 is primary of view 1 and announces it once a quorum has voted
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node1, peers[1], MessageType::ViewChange(1), &mut out);
+        assert!(out.is_empty());
+        actor.on_msg(peers[1], &mut node1, peers[2], MessageType::ViewChange(1), &mut out);
+        assert_eq!(out.len(), peers.len());
+        assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::NewView(1, _)))));
+
+        // Other nodes enter the new view and prepare its value
+        let mut node2 = Cow::Owned(ConsensusNodeState::new(2, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[2], &mut node2, peers[1], MessageType::NewView(1, Value::V2), &mut out);
+        assert_eq!(node2.view, 1);
+        assert_eq!(node2.state, NodeState::PrePrepared);
+        assert_eq!(node2.value, Some(Value::V2));
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::Prepare { seq: 1, view: 1, value: Value::V2 })
+        )));
+
+        // NEW-VIEW from a node that is not that view's primary is ignored
+        let mut node0 = Cow::Owned(ConsensusNodeState::new(0, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut node0, peers[2], MessageType::NewView(1, Value::V2), &mut out);
+        assert!(out.is_empty());
+        assert_eq!(node0.view, 0);
+    }
+
+    #[test]
+    fn test_no_op() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let builder = || ConsensusActor::builder().peers(peers.clone()).quorum(2).view_changes(true).no_op(Value::Bottom);
+        let actor = builder().build().unwrap();
+        assert!(Value::Bottom < Value::V1 && Value::Bottom.is_bottom());
+
+        // A new primary with nothing to carry over gives up on the instance
+        let mut node1 = Cow::Owned(ConsensusNodeState::new(1, 2));
+        let mut out = Out::new();
+        for voter in [1, 2] {
+            actor.on_msg(peers[1], &mut node1, peers[voter], MessageType::ViewChange(1), &mut out);
+        }
+        assert_eq!(out.len(), peers.len());
+        assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::NewView(1, Value::Bottom)))));
+
+        // One that accepted a value re-proposes it instead
+        let mut node1 = Cow::Owned(ConsensusNodeState { value: Some(Value::V2), ..ConsensusNodeState::new(1, 2) });
+        let mut out = Out::new();
+        for voter in [1, 2] {
+            actor.on_msg(peers[1], &mut node1, peers[voter], MessageType::ViewChange(1), &mut out);
+        }
+        assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::NewView(1, Value::V2)))));
+
+        // Aborting must be among the allowed values, if they are restricted
+        assert!(builder().num_values(1).allowed_values(BTreeSet::from([Value::V1])).build().is_err());
+        assert!(builder().num_values(1).allowed_values(BTreeSet::from([Value::V1, Value::Bottom])).build().is_ok());
+    }
+
+    #[test]
+    fn test_locking() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor {
+            view_changes: true,
+            ..ConsensusActor::new(peers.clone(), 2)
+        };
+
+        // Sending COMMIT for V1 in view 0 locks node 2 on it
+        let mut node = ConsensusNodeState::new(2, 2);
+        node.value = Some(Value::V1);
+        node.state = NodeState::PrePrepared;
+        node.record_prepare(&Stake::default(), 1, &Value::V1, 2);
+        let mut node = Cow::Owned(node);
+        let mut out = Out::new();
+        actor.on_msg(peers[2], &mut node, peers[0], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.state, NodeState::Prepared);
+        assert_eq!(node.locked_value, Some(Value::V1));
+        assert_eq!(node.locked_round, 0);
+
+        // A later view's V2 is not prepared, though the node does move to that view
+        node.to_mut().state = NodeState::ViewChanging;
+        let mut out = Out::new();
+        actor.on_msg(peers[2], &mut node, peers[1], MessageType::NewView(1, Value::V2), &mut out);
+        assert_eq!(node.view, 1);
+        assert_eq!(node.state, NodeState::Init);
+        assert_eq!(node.value, None);
+        assert!(out.is_empty());
+
+        // until a quorum of the others' PREPAREs for V2 unlocks it
+        let prepare = MessageType::Prepare { seq: 1, view: 1, value: Value::V2 };
+        actor.on_msg(peers[2], &mut node, peers[1], prepare.clone(), &mut out);
+        assert_eq!(node.locked_value, Some(Value::V1));
+        assert!(out.is_empty());
+        actor.on_msg(peers[2], &mut node, peers[0], prepare, &mut out);
+        assert_eq!(node.locked_value, Some(Value::V2));
+        assert_eq!(node.locked_round, 1);
+        assert_eq!(node.value, Some(Value::V2));
+        assert_eq!(node.state, NodeState::Prepared);
+        assert!(out.iter().any(|c| matches!(c, Command::Send(_, MessageType::Commit { view: 1, value: Value::V2, .. }))));
+
+        // A NEW-VIEW for the locked value is prepared as usual
+        let mut locked = ConsensusNodeState::new(2, 2);
+        locked.locked_value = Some(Value::V1);
+        let mut locked = Cow::Owned(locked);
+        actor.on_msg(peers[2], &mut locked, peers[1], MessageType::NewView(1, Value::V1), &mut Out::new());
+        assert_eq!(locked.state, NodeState::PrePrepared);
+        assert_eq!(locked.value, Some(Value::V1));
+    }
+
+    #[test]
+    fn test_mid_protocol_crash() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::with_faults(peers.clone(), vec![2], 2);
+
+        // A crash-faulty node starts out live, with a pending crash
+        let mut out = Out::new();
+        let mut state: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[2], &None, &mut out));
+        assert!(state.is_faulty);
+        assert_eq!(state.state, NodeState::Init);
+        assert!(out.iter().any(|c| matches!(c, Command::SetTimer(ConsensusTimer::Crash, _))));
+
+        // It follows the protocol until the timer fires
+        let mut out = Out::new();
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[2], &mut state, peers[0], pre_prepare, &mut out);
+        assert_eq!(state.state, NodeState::PrePrepared);
+        assert_eq!(out.len(), peers.len());
+
+        // Once crashed it ignores everything
+        let mut out = Out::new();
+        actor.on_timeout(peers[2], &mut state, &ConsensusTimer::Crash, &mut out);
+        assert_eq!(state.state, NodeState::Failed);
+        let mut out = Out::new();
+        actor.on_msg(peers[2], &mut state, peers[1], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(state.prepare_count.get(&(1, Value::V1)), Some(&1));
+    }
+
+    #[test]
+    fn test_replay() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers, 2);
+
+        // Node 1 completes the normal case on votes from node 2 alone
+        let states = actor.replay(&[
+            TraceStep::Propose { node: 0, value: Value::V2 },
+            TraceStep::DeliverPrePrepare { src: 0, dst: 1, value: Value::V2 },
+            TraceStep::DeliverPrepare { src: 2, dst: 1, value: Value::V2 },
+            TraceStep::DeliverCommit { src: 2, dst: 1, value: Value::V2 },
+            TraceStep::DeliverDecide { src: 2, dst: 1, value: Value::V2 },
+            TraceStep::Crash { node: 2 },
+        ]).unwrap();
+        assert_eq!(states[1].state, NodeState::Decided);
+        assert_eq!(states[1].value, Some(Value::V2));
+        assert_eq!(states[1].proposer, Some(0));
+        assert_eq!(states[2].state, NodeState::Failed);
+        assert!(states[2].is_faulty);
+
+        // The primary pre-prepared the proposal, but nothing was delivered back to it
+        assert!(states[0].has_proposed);
+        assert_eq!(states[0].value, None);
+
+        // A step naming a node outside the peers is refused, not applied
+        let stray = [
+            TraceStep::Propose { node: 0, value: Value::V2 },
+            TraceStep::DeliverPrepare { src: 5, dst: 1, value: Value::V2 },
+        ];
+        assert_eq!(actor.replay(&stray), Err(1));
+        assert_eq!(actor.replay(&[TraceStep::Crash { node: 3 }]), Err(0));
+    }
+
+    #[test]
+    fn test_initial_proposals() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+
+        // By default the proposer proposes all three values to every peer
+        let actor = ConsensusActor::new(peers.clone(), 2);
+        let mut out = Out::new();
+        actor.on_start(peers[0], &None, &mut out);
+        assert_eq!(out.len(), 3 * peers.len());
+
+        // Configured with just V2, that is all it proposes
+        let actor = ConsensusActor::builder().peers(peers.clone()).quorum(2).initial_proposals(vec![Value::V2]).build().unwrap();
+        let mut out = Out::new();
+        actor.on_start(peers[0], &None, &mut out);
+        assert_eq!(out.len(), peers.len());
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::Propose { seq: 1, view: 0, value: Value::V2, proposer: 0 })
+        )));
+    }
+
+    #[test]
+    fn test_initial_values() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let inputs = HashMap::from([(0, Value::V1), (2, Value::V3)]);
+        let actor = ConsensusActor::builder().peers(peers.clone()).quorum(2).initial_values(inputs).build().unwrap();
+
+        // Every node with an input proposes it, and only it; the others propose nothing
+        for (node, value) in [(0, Some(Value::V1)), (1, None), (2, Some(Value::V3))] {
+            let mut out = Out::new();
+            actor.on_start(peers[node], &None, &mut out);
+            let proposed: Vec<_> = out
+                .iter()
+                .filter_map(|c| match c {
+                    Command::Send(_, MessageType::Propose { value, proposer, .. }) if *proposer == node => Some(value.clone()),
+                    _ => None,
+                })
+                .collect();
+            let expected = value.map(|value| vec![value; peers.len()]).unwrap_or_default();
+            assert_eq!(proposed, expected);
+        }
+
+        // A new primary holding nothing starts afresh with its own input, or else with
+        // one some node did propose, never with a value nobody put forward
+        let actor = ConsensusActor { view_changes: true, ..actor };
+        for (view, value) in [(1, Value::V1), (2, Value::V3)] {
+            let primary = peers[actor.primary(view)];
+            let mut node = Cow::Owned(ConsensusNodeState::new(usize::from(primary), 2));
+            let mut out = Out::new();
+            for voter in [0, 2] {
+                actor.on_msg(primary, &mut node, peers[voter], MessageType::ViewChange(view), &mut out);
+            }
+            assert_eq!(out.len(), peers.len());
+            assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::NewView(v, new)) if *v == view && *new == value)));
         }
+
+        // Inputs belong to peers
+        let stranger = HashMap::from([(3, Value::V1)]);
+        assert!(ConsensusActor::builder().peers(peers).quorum(2).initial_values(stranger).build().is_err());
+
+        // Values parse as printed
+        assert_eq!("V2".parse::<Value>(), Ok(Value::V2));
+        assert_eq!("v5".parse::<Value>(), Ok(Value::Custom(5)));
+        assert_eq!("bottom".parse::<Value>(), Ok(Value::Bottom));
+        assert!("V0".parse::<Value>().is_err());
+        assert!("X1".parse::<Value>().is_err());
     }
 
-    fn on_timeout(
-        &self,
-        _id: Id,
-        state: &mut Cow<Self::State>,
-        timer: &Self::Timer,
-        o: &mut Out<Self>,
-    ) {
-        // Don't process timers if node is faulty
-        if state.is_faulty {
-            return;
+    #[test]
+    fn test_display() {
+        // Values print as they parse, and back
+        for value in Value::domain(6) {
+            assert_eq!(value.to_string().parse::<Value>(), Ok(value));
         }
+        assert_eq!(Value::Custom(4).to_string(), "V4");
+        assert_eq!(Value::Bottom.to_string().parse::<Value>(), Ok(Value::Bottom));
 
-        match timer {
-            ConsensusTimer::ProposeValue(value) => {
-                // Only propose if:
-                // 1. We're still in Init state (haven't accepted a proposal yet)
-                // 2. We haven't already proposed
-                if state.state == NodeState::Init && !state.has_proposed {
-                    let mut new_state = state.as_ref().clone();
-                    new_state.has_proposed = true;
+        // Custom values that would pass for V1 to V3 print apart from them
+        for value in [Value::Custom(0), Value::Custom(2), Value::Custom(255)] {
+            assert_eq!(value.to_string().parse::<Value>(), Ok(value));
+        }
+        assert_eq!(Value::Custom(2).to_string(), "Custom(2)");
+        assert!("Custom(256)".parse::<Value>().is_err());
 
-                    // Broadcast PROPOSE to ALL nodes (including self per TLA+ spec)
-                    for &peer in &self.peers {
-                        o.send(peer, MessageType::Propose(value.clone()));
-                    }
+        assert_eq!(NodeState::Init.to_string(), "INIT");
+        assert_eq!(NodeState::PrePrepared.to_string(), "PRE-PREPARED");
+        assert_eq!(NodeState::Prepared.to_string(), "PREPARED");
+        assert_eq!(NodeState::ViewChanging.to_string(), "VIEW-CHANGING");
 
-                    *state = Cow::Owned(new_state);
-                }
-            }
+        let propose = MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 2 };
+        assert_eq!(propose.to_string(), "PROPOSE(V1, seq 1, view 0, proposer 2)");
+        let pre_prepare = MessageType::PrePrepare { seq: 2, view: 1, value: Value::V3, proposer: 0 };
+        assert_eq!(pre_prepare.to_string(), "PRE-PREPARE(V3, seq 2, view 1, proposer 0)");
+        assert_eq!(MessageType::Commit { seq: 1, view: 0, value: Value::V2 }.to_string(), "COMMIT(V2, seq 1, view 0)");
+        assert_eq!(MessageType::<Value>::ViewChange(1).to_string(), "VIEW-CHANGE(view 1)");
+        assert_eq!(MessageType::NewView(1, Value::V2).to_string(), "NEW-VIEW(V2, view 1)");
+        assert_eq!(MessageType::<Value>::Checkpoint(3).to_string(), "CHECKPOINT(seq 3)");
+
+        // Any displayable value will do
+        assert_eq!(MessageType::Decide { seq: 1, view: 0, value: 7u32 }.to_string(), "DECIDE(7, seq 1, view 0)");
+    }
+
+    #[test]
+    fn test_proposer_id() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::builder().peers(peers.clone()).quorum(2).proposer(2).num_values(1).build().unwrap();
+
+        // Only the configured proposer proposes at start-up
+        let mut out = Out::new();
+        actor.on_start(peers[0], &None, &mut out);
+        assert!(out.is_empty());
+        actor.on_start(peers[2], &None, &mut out);
+        assert_eq!(out.len(), peers.len());
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 2 })
+        )));
+
+        // The primary still orders its proposal
+        let mut primary = Cow::Owned(ConsensusNodeState::new(0, 2));
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut primary, peers[2], MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 2 }, &mut out);
+        assert!(out.iter().all(|c| matches!(
+            c,
+            Command::Send(_, MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 2 })
+        )));
+
+        // A crash-faulty proposer proposes before crashing; a mute one stays silent
+        let crashing = ConsensusActor::builder().peers(peers.clone()).quorum(2).faulty(vec![2]).proposer(2).build().unwrap();
+        let mut out = Out::new();
+        crashing.on_start(peers[2], &None, &mut out);
+        assert!(out.iter().any(|c| matches!(c, Command::Send(..))));
+        let mute = ConsensusActor { fault_kind: FaultKind::Mute, ..crashing };
+        let mut out = Out::new();
+        mute.on_start(peers[2], &None, &mut out);
+        assert!(out.is_empty());
+
+        // The proposer must be one of the peers
+        assert!(ConsensusActor::builder().peers(peers).quorum(2).proposer(3).build().is_err());
+    }
+
+    #[test]
+    fn test_non_contiguous_peers() {
+        // A sub-committee of nodes 3, 5, 6 and 9, without a node 0
+        let ids = [3, 5, 6, 9];
+        let peers: Vec<Id> = ids.iter().copied().map(Id::from).collect();
+        let actor = ConsensusActor::builder().peers(peers.clone()).quorum(3).num_values(1).build().unwrap();
+        assert_eq!(actor.proposer_id, 3);
+        assert_eq!((actor.primary(0), actor.primary(1)), (3, 5));
+
+        // The first peer proposes and, as primary, pre-prepares; every node decides
+        let mut out = Out::new();
+        actor.on_start(peers[0], &None, &mut out);
+        assert_eq!(out.len(), peers.len());
+        let others = |node: NodeId| ids.into_iter().filter(move |&src| src != node);
+        let mut steps = vec![TraceStep::Propose { node: 3, value: Value::V1 }];
+        steps.extend(ids.map(|dst| TraceStep::DeliverPrePrepare { src: 3, dst, value: Value::V1 }));
+        steps.extend(ids.into_iter().flat_map(|dst| others(dst).map(move |src| TraceStep::DeliverPrepare { src, dst, value: Value::V1 })));
+        steps.extend(ids.into_iter().flat_map(|dst| others(dst).map(move |src| TraceStep::DeliverCommit { src, dst, value: Value::V1 })));
+        steps.extend(ids.map(|dst| TraceStep::DeliverDecide { src: dst, dst, value: Value::V1 }));
+        let states = actor.replay(&steps).unwrap();
+        assert!(states.iter().all(|s| s.decisions.get(&1) == Some(&Value::V1)));
+        assert_eq!(states.iter().map(|s| s.id).collect::<Vec<_>>(), ids);
+
+        // Any peer may propose instead, but no node outside the committee
+        let later = ConsensusActor::builder().peers(peers.clone()).quorum(3).proposer(6).build().unwrap();
+        let mut out = Out::new();
+        later.on_start(peers[0], &None, &mut out);
+        assert!(out.is_empty());
+        later.on_start(peers[2], &None, &mut out);
+        assert!(!out.is_empty());
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).proposer(0).build().is_err());
+        assert!(ConsensusActor::builder().peers(peers).quorum(3).faulty(vec![4]).build().is_err());
+    }
+
+    #[test]
+    fn test_delays() {
+        let delays = Delays { slow_nodes: BTreeSet::from([1]), view_change_timeout: DelayClass::Fast };
+        assert_eq!(delays.class(0), DelayClass::Fast);
+        assert_eq!(delays.class(1), DelayClass::Slow);
+
+        // A fast timeout waits out fast messages but not slow ones, nor any vote to change views
+        let prepare = MessageType::Prepare { seq: FIRST_SEQ, view: 0, value: Value::V1 };
+        assert!(!delays.may_time_out([(0, &prepare)].into_iter()));
+        assert!(delays.may_time_out([(1, &prepare), (0, &MessageType::ViewChange(1))].into_iter()));
+        let slow_timeout = Delays { view_change_timeout: DelayClass::Slow, ..delays };
+        assert!(!slow_timeout.may_time_out([(1, &prepare)].into_iter()));
+    }
+
+    #[test]
+    fn test_ignores() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::builder().peers(peers).quorum(2).num_values(1).build().unwrap();
+        let mut node = ConsensusNodeState::new(1, 2);
+        let pre_prepare = MessageType::PrePrepare { seq: FIRST_SEQ, view: 0, value: Value::V1, proposer: 0 };
+        assert!(!actor.ignores(&node, 0, &pre_prepare));
+        assert!(actor.ignores(&node, 2, &pre_prepare));  // Not from the primary
+
+        // Having started a view change, the node would still have used it had it waited
+        node.state = NodeState::ViewChanging;
+        assert!(!actor.ignores(&node, 0, &pre_prepare));
+        node.value = Some(Value::V1);
+        assert!(actor.ignores(&node, 0, &pre_prepare));
+    }
+
+    #[test]
+    fn test_progress() {
+        // A view change outranks every phase before a decision
+        assert!(NodeState::Committed < NodeState::ViewChanging);
+        assert!(NodeState::ViewChanging < NodeState::Decided);
+
+        // Entering a new view resets the phase but still moves the node forward
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::builder().peers(peers.clone()).quorum(2).num_values(1).build().unwrap();
+        let mut node = ConsensusNodeState::new(2, 2);
+        node.value = Some(Value::V1);
+        node.state = NodeState::ViewChanging;
+        node.furthest = node.progress();
+        let mut node = Cow::Owned(node);
+        actor.on_msg(peers[2], &mut node, peers[1], MessageType::NewView(1, Value::V1), &mut Out::new());
+        assert_eq!(node.progress(), (FIRST_SEQ, 1, NodeState::PrePrepared));
+        assert_eq!(node.furthest, node.progress());
+
+        // The furthest progress is kept when a node falls back
+        node.to_mut().view = 0;
+        ConsensusNodeState::note_progress(&mut node);
+        assert!(node.progress() < node.furthest);
+    }
+
+    #[test]
+    fn test_multiple_instances() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .quorum(2)
+            .num_values(1)
+            .mode(ConsensusMode::Log)
+            .instances(2)
+            .build()
+            .unwrap();
+
+        // The proposer proposes for every instance at start-up
+        let mut out = Out::new();
+        actor.on_start(peers[0], &None, &mut out);
+        assert_eq!(out.len(), 2 * peers.len());
+        assert!(out.iter().any(|c| matches!(c, Command::Send(_, MessageType::Propose { seq: 2, .. }))));
+
+        // Deciding instance 1 records it and moves the node on to instance 2
+        let mut node = ConsensusNodeState::new(1, 2);
+        node.value = Some(Value::V1);
+        node.state = NodeState::Committed;
+        node.commit_count.insert((1, Value::V1), 2);
+        let mut node = Cow::Owned(node);
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], MessageType::Decide { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.decisions.get(&1), Some(&Value::V1));
+        assert_eq!(node.seq, 2);
+        assert_eq!(node.state, NodeState::Init);
+        assert!(!node.decided);
+
+        // Votes for instance 1 no longer count, and never towards instance 2
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], MessageType::PrePrepare { seq: 2, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        actor.on_msg(peers[1], &mut node, peers[2], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.prepare_count.get(&(2, Value::V1)), Some(&1));
+        assert_eq!(node.state, NodeState::PrePrepared);
+        actor.on_msg(peers[1], &mut node, peers[2], MessageType::Prepare { seq: 2, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.prepare_count.get(&(2, Value::V1)), Some(&2));
+        assert_eq!(node.state, NodeState::Prepared);
+        assert_eq!(node.commit_count.get(&(1, Value::V1)), Some(&2));
+
+        // The last instance stays decided
+        node.to_mut().state = NodeState::Committed;
+        actor.on_msg(peers[1], &mut node, peers[0], MessageType::Decide { seq: 2, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.seq, 2);
+        assert_eq!(node.state, NodeState::Decided);
+        assert_eq!(node.decisions.len(), 2);
+    }
+
+    #[test]
+    fn test_conflicting_proposal() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+        let propose = |value| MessageType::Propose { seq: 1, view: 0, value, proposer: 0 };
+
+        // A backup that accepted V1 notes a proposal for V2 but keeps its value
+        let mut node = ConsensusNodeState::new(1, 2);
+        node.value = Some(Value::V1);
+        node.state = NodeState::PrePrepared;
+        let mut node = Cow::Borrowed(&node);
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], propose(Value::V1), &mut out);
+        assert!(!node.conflicting_proposal);
+        actor.on_msg(peers[1], &mut node, peers[0], propose(Value::V2), &mut out);
+        assert!(node.conflicting_proposal);
+        assert_eq!(node.value, Some(Value::V1));
+        assert!(!node.changed_value);
+        assert!(out.is_empty());
+
+        // Replacing the value within the view is flagged, unlike moving on to a new one
+        let mut replaced: Cow<ConsensusNodeState> = Cow::Owned(node.clone().into_owned());
+        let before = replaced.accepted();
+        replaced.to_mut().value = Some(Value::V2);
+        ConsensusNodeState::note_value(&mut replaced, before);
+        assert!(replaced.changed_value);
+        let mut next_view: Cow<ConsensusNodeState> = Cow::Owned(node.into_owned());
+        let before = next_view.accepted();
+        next_view.to_mut().view = 1;
+        next_view.to_mut().value = Some(Value::V2);
+        ConsensusNodeState::note_value(&mut next_view, before);
+        assert!(!next_view.changed_value);
+    }
+
+    #[test]
+    fn test_decide_needs_commit() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+        let decide = MessageType::Decide { seq: 1, view: 0, value: Value::V1 };
+
+        // A merely Prepared node has not committed, so a DECIDE doesn't count yet
+        let mut node = ConsensusNodeState::new(1, 2);
+        node.value = Some(Value::V1);
+        node.state = NodeState::Prepared;
+        let mut node = Cow::Owned(node);
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], decide.clone(), &mut out);
+        assert!(!node.decided);
+        assert_eq!(node.state, NodeState::Prepared);
+        assert!(out.is_empty());
+
+        // Nor for a node that timed out before committing
+        node.to_mut().state = NodeState::ViewChanging;
+        actor.on_msg(peers[1], &mut node, peers[0], decide.clone(), &mut out);
+        assert!(!node.decided);
+
+        // but it does for one that timed out after its COMMIT quorum
+        node.to_mut().record_commit(&Stake::default(), 1, &Value::V1, 0);
+        node.to_mut().record_commit(&Stake::default(), 1, &Value::V1, 1);
+        actor.on_msg(peers[1], &mut node, peers[0], decide, &mut out);
+        assert!(node.decided);
+        assert_eq!(node.state, NodeState::Decided);
+    }
+
+    #[test]
+    fn test_reached_prepare_quorum() {
+        let mut node = ConsensusNodeState::new(1, 2);
+        node.record_prepare(&Stake::default(), 1, &Value::V1, 1);
+        assert_eq!(node.reached_prepare_quorum.get(&(1, Value::V1)), None);
+        node.record_prepare(&Stake::default(), 1, &Value::V1, 0);
+        assert_eq!(node.reached_prepare_quorum.get(&(1, Value::V1)), Some(&true));
+
+        // A repeated PREPARE changes nothing, and other values have their own quorum
+        assert_eq!(node.record_prepare(&Stake::default(), 1, &Value::V1, 0), None);
+        node.record_prepare(&Stake::default(), 1, &Value::V2, 0);
+        assert_eq!(node.reached_prepare_quorum.get(&(1, Value::V2)), None);
+
+        // COMMIT quorums are noted the same way
+        node.record_commit(&Stake::default(), 1, &Value::V1, 1);
+        assert_eq!(node.reached_commit_quorum.get(&(1, Value::V1)), None);
+        node.record_commit(&Stake::default(), 1, &Value::V1, 0);
+        assert_eq!(node.reached_commit_quorum.get(&(1, Value::V1)), Some(&true));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::new(peers.clone(), 2);
+        let mut node: Cow<ConsensusNodeState> = Cow::Owned(ConsensusNodeState::new(1, 2));
+        node.to_mut().value = Some(Value::V1);
+        node.to_mut().state = NodeState::Committed;
+        node.to_mut().prepare_count.insert((1, Value::V1), 2);
+        node.to_mut().commit_count.insert((1, Value::V1), 2);
+
+        // Deciding broadcasts a checkpoint for the instance
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], MessageType::Decide { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert!(node.decided);
+        assert!(out.iter().all(|c| matches!(c, Command::Send(_, MessageType::Checkpoint(FIRST_SEQ)))));
+
+        // A quorum of checkpoints makes it stable and drops the counts
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[1], MessageType::Checkpoint(FIRST_SEQ), &mut out);
+        assert_eq!(node.checkpoint, None);
+        actor.on_msg(peers[1], &mut node, peers[2], MessageType::Checkpoint(FIRST_SEQ), &mut out);
+        assert_eq!(node.checkpoint, Some(FIRST_SEQ));
+        assert!(node.prepare_count.is_empty());
+        assert!(node.commit_count.is_empty());
+
+        // Late messages for the checkpointed instance don't bring counts back
+        actor.on_msg(peers[1], &mut node, peers[2], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert!(node.prepare_count.is_empty());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_partition() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let partition = Partition { group: BTreeSet::from([0]) };
+        assert!(partition.separates(0, 2));
+        assert!(!partition.separates(1, 2));
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .quorum(2)
+            .num_values(1)
+            .partition(Some(partition))
+            .build()
+            .unwrap();
+
+        // The proposer's proposals stay on its own side, and every node may heal
+        let mut out = Out::new();
+        let mut primary: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[0], &None, &mut out));
+        assert!(primary.partitioned);
+        assert!(out.iter().any(|c| matches!(c, Command::SetTimer(ConsensusTimer::HealPartition, _))));
+        assert!(out.iter().all(|c| !matches!(c, Command::Send(dst, _) if *dst != peers[0])));
+
+        // Once healed, its PRE-PREPARE reaches everyone
+        let mut out = Out::new();
+        actor.on_timeout(peers[0], &mut primary, &ConsensusTimer::HealPartition, &mut out);
+        assert!(!primary.partitioned);
+        actor.on_msg(peers[0], &mut primary, peers[0], MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        assert_eq!(out.len(), peers.len());
+
+        // Nodes on the same side still talk while partitioned
+        let mut backup: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[1], &None, &mut Out::new()));
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut backup, peers[0], MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 }, &mut out);
+        assert_eq!(out.len(), 2);
+        assert!(out.iter().all(|c| matches!(c, Command::Send(dst, _) if *dst != peers[0])));
+    }
+
+    #[test]
+    fn test_reset_for_new_instance() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .faulty(vec![2])
+            .quorum(2)
+            .mode(ConsensusMode::Log)
+            .instances(2)
+            .build()
+            .unwrap();
+        let pre_prepare = |seq| MessageType::PrePrepare { seq, view: 0, value: Value::V1, proposer: 0 };
+        let prepare = MessageType::Prepare { seq: 1, view: 0, value: Value::V1 };
+
+        for id in [1, 2] {
+            let fresh = actor.on_start(peers[id], &None, &mut Out::new());
+            let mut node = Cow::Owned(fresh.clone());
+            actor.on_msg(peers[id], &mut node, peers[0], pre_prepare(1), &mut Out::new());
+            actor.on_msg(peers[id], &mut node, peers[0], prepare.clone(), &mut Out::new());
+            assert_ne!(*node, fresh);
+
+            // A reset node is the one just started, faulty or not, but one instance on
+            let mut node = node.into_owned();
+            node.reset_for_new_instance();
+            assert_eq!(node.seq, 2);
+            assert_eq!(ConsensusNodeState { seq: 1, ..node.clone() }, fresh);
+
+            // and handles the next instance's messages as that one handles the first's
+            let (mut reset, mut started) = (Cow::Owned(node), Cow::Owned(fresh));
+            let (mut reset_out, mut started_out) = (Out::new(), Out::new());
+            actor.on_msg(peers[id], &mut reset, peers[0], pre_prepare(2), &mut reset_out);
+            actor.on_msg(peers[id], &mut started, peers[0], pre_prepare(1), &mut started_out);
+            assert_eq!((reset.state.clone(), reset.value.clone()), (started.state.clone(), started.value.clone()));
+            assert_eq!(reset_out.len(), started_out.len());
+            assert_eq!(reset.value, Some(Value::V1));
         }
     }
-}
 
-/// Model configuration for testing
-#[allow(dead_code)]
-pub struct ConsensusModel {
-    pub num_nodes: usize,
-    pub max_faults: usize,
-}
+    #[test]
+    fn test_buffered_prepares() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let builder = || ConsensusActor::builder().peers(peers.clone()).quorum(2).num_values(1);
+        let prepare = MessageType::Prepare { seq: 1, view: 0, value: Value::V1 };
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
 
-#[allow(dead_code)]
-impl ConsensusModel {
-    pub fn new(num_nodes: usize, max_faults: usize) -> Self {
-        ConsensusModel {
-            num_nodes,
-            max_faults,
+        // Without buffering, a PREPARE ahead of the PRE-PREPARE changes nothing
+        let actor = builder().build().unwrap();
+        let started = actor.on_start(peers[1], &None, &mut Out::new());
+        let mut node = Cow::Borrowed(&started);
+        actor.on_msg(peers[1], &mut node, peers[2], prepare.clone(), &mut Out::new());
+        assert!(matches!(node, Cow::Borrowed(_)));
+
+        // With it, the PREPARE is kept, and only once
+        let actor = builder().buffer_prepares(true).build().unwrap();
+        let mut node: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[1], &None, &mut Out::new()));
+        actor.on_msg(peers[1], &mut node, peers[2], prepare.clone(), &mut Out::new());
+        actor.on_msg(peers[1], &mut node, peers[2], prepare.clone(), &mut Out::new());
+        assert_eq!(node.pending_prepares, HashMap::from([(Value::V1, BTreeSet::from([2]))]));
+        assert_eq!(node.prepare_count.get(&(1, Value::V1)), None);
+
+        // and counted with our own on the PRE-PREPARE, making a quorum right away
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut node, peers[0], pre_prepare, &mut out);
+        assert_eq!(node.state, NodeState::Prepared);
+        assert_eq!(node.prepare_count.get(&(1, Value::V1)), Some(&2));
+        assert!(node.pending_prepares.is_empty());
+        assert!(out.iter().any(|c| matches!(c, Command::Send(_, MessageType::Commit { .. }))));
+    }
+
+    #[test]
+    fn test_allowed_values() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let builder = || ConsensusActor::builder().peers(peers.clone()).quorum(2).num_values(1);
+        let actor = builder().allowed_values(BTreeSet::from([Value::V1])).build().unwrap();
+        let started = actor.on_start(peers[1], &None, &mut Out::new());
+        let mut node = Cow::Borrowed(&started);
+        let pre_prepare = |value| MessageType::PrePrepare { seq: 1, view: 0, value, proposer: 0 };
+
+        // A value outside the domain is rejected as malformed and dropped, leaving
+        // the node as it was, while messages for allowed values are handled as usual
+        let messages = [
+            (peers[0], pre_prepare(Value::Custom(9))),
+            (peers[2], MessageType::Prepare { seq: 1, view: 0, value: Value::V2 }),
+            (peers[0], pre_prepare(Value::V1)),
+        ];
+        assert_eq!(messages.iter().filter(|(_, msg)| actor.rejects(msg)).count(), 2);
+        for (src, msg) in messages[..2].iter().cloned() {
+            actor.on_msg(peers[1], &mut node, src, msg, &mut Out::new());
         }
+        assert!(matches!(node, Cow::Borrowed(_)));
+        let (src, msg) = messages[2].clone();
+        actor.on_msg(peers[1], &mut node, src, msg, &mut Out::new());
+        assert_eq!(node.value, Some(Value::V1));
+
+        // Without a domain every value is taken, and the proposer's values must be allowed
+        let mut node = Cow::Borrowed(&started);
+        let open = builder().build().unwrap();
+        assert!(!open.rejects(&pre_prepare(Value::V2)));
+        open.on_msg(peers[1], &mut node, peers[0], pre_prepare(Value::V2), &mut Out::new());
+        assert_eq!(node.value, Some(Value::V2));
+        assert!(builder().allowed_values(BTreeSet::from([Value::V2])).build().is_err());
     }
 
-    /// Check safety property: Agreement
-    pub fn check_agreement(&self, history: &[ConsensusNodeState]) -> bool {
-        let decided_values: Vec<_> = history
+    #[test]
+    fn test_observers() {
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let builder = |observer: NodeId| {
+            ConsensusActor::builder()
+                .peers(peers.clone())
+                .quorum(2)
+                .num_values(1)
+                .roles(HashMap::from([(observer, NodeRole::Observer)]))
+        };
+        let actor = builder(3).build().unwrap();
+        assert!(actor.is_observer(3));
+        assert_eq!(actor.role_of(1), NodeRole::Participant);
+        assert_eq!(actor.participants(), 3);
+
+        // An observer proposes nothing and ignores the vote, however many arrive
+        let mut out = Out::new();
+        let started = actor.on_start(peers[3], &None, &mut out);
+        assert!(out.is_empty());
+        let mut node = Cow::Borrowed(&started);
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[3], &mut node, peers[0], pre_prepare, &mut out);
+        for src in 0..3 {
+            actor.on_msg(peers[3], &mut node, peers[src], MessageType::Prepare { seq: 1, view: 0, value: Value::V1 }, &mut out);
+            actor.on_msg(peers[3], &mut node, peers[src], MessageType::Commit { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        }
+        assert!(matches!(node, Cow::Borrowed(_)));
+        assert!(out.is_empty());
+
+        // but takes the first DECIDE as the decision, and sends nothing back
+        actor.on_msg(peers[3], &mut node, peers[1], MessageType::Decide { seq: 1, view: 0, value: Value::V1 }, &mut out);
+        assert_eq!(node.state, NodeState::Decided);
+        assert_eq!(node.decisions.get(&1), Some(&Value::V1));
+        assert!(out.is_empty());
+
+        // Observers can't be anyone the protocol relies on
+        assert!(builder(0).build().is_err());
+        assert!(builder(4).build().is_err());
+        assert!(builder(3).faulty(vec![3]).build().is_err());
+        assert!(builder(3).initial_values(HashMap::from([(3, Value::V2)])).build().is_err());
+        assert!(builder(3).view_changes(true).build().is_err());
+    }
+
+    #[test]
+    fn test_retry() {
+        let peers: Vec<Id> = (0..2).map(Id::from).collect();
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .quorum(2)
+            .num_values(1)
+            .max_retries(2)
+            .build()
+            .unwrap();
+        let propose = MessageType::Propose { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+
+        // The proposer re-sends its PROPOSE, and may retry once more
+        let mut out = Out::new();
+        let mut proposer: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[0], &None, &mut out));
+        assert!(out.iter().any(|c| matches!(c, Command::SetTimer(ConsensusTimer::Retry, _))));
+        let mut out = Out::new();
+        actor.on_timeout(peers[0], &mut proposer, &ConsensusTimer::Retry, &mut out);
+        assert_eq!(proposer.retry_count, 1);
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, msg) if *msg == propose)).count(), 2);
+        assert!(out.iter().any(|c| matches!(c, Command::SetTimer(ConsensusTimer::Retry, _))));
+
+        // A pre-prepared primary re-sends its PRE-PREPARE and PREPARE, and stops there
+        actor.on_msg(peers[0], &mut proposer, peers[0], propose.clone(), &mut Out::new());
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[0], &mut proposer, peers[0], pre_prepare.clone(), &mut Out::new());
+        let mut out = Out::new();
+        actor.on_timeout(peers[0], &mut proposer, &ConsensusTimer::Retry, &mut out);
+        assert_eq!(proposer.retry_count, 2);
+        let prepare = MessageType::Prepare { seq: 1, view: 0, value: Value::V1 };
+        let sent: Vec<_> = out
             .iter()
-            .filter(|s| s.decided && !s.is_faulty)
-            .filter_map(|s| s.value.as_ref())
+            .filter_map(|c| match c {
+                Command::Send(dst, msg) if *dst == peers[1] => Some(msg.clone()),
+                _ => None,
+            })
             .collect();
+        assert_eq!(sent, [pre_prepare, prepare]);
+        assert!(!out.iter().any(|c| matches!(c, Command::SetTimer(..))));
 
-        // All non-faulty decided nodes must have the same value
-        if decided_values.len() > 1 {
-            let first = decided_values[0];
-            decided_values.iter().all(|&v| v == first)
-        } else {
-            true
+        // Out of retries, nothing more is sent
+        let mut out = Out::new();
+        actor.on_timeout(peers[0], &mut proposer, &ConsensusTimer::Retry, &mut out);
+        assert!(out.is_empty());
+        assert_eq!(proposer.retry_count, 2);
+    }
+
+    #[test]
+    fn test_mute() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = ConsensusActor {
+            fault_kind: FaultKind::Mute,
+            view_changes: true,
+            ..ConsensusActor::with_faults(peers.clone(), vec![2], 2)
+        };
+
+        // A mute node starts like any other and keeps its timers
+        let mut out = Out::new();
+        let mut state: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[2], &None, &mut out));
+        assert!(state.is_faulty);
+        assert_eq!(state.state, NodeState::Init);
+        assert!(out.iter().any(|c| matches!(c, Command::SetTimer(ConsensusTimer::ViewChange, _))));
+
+        // It tracks the protocol like an honest node but sends nothing
+        let mut out = Out::new();
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[2], &mut state, peers[0], pre_prepare, &mut out);
+        assert_eq!(state.state, NodeState::PrePrepared);
+        assert_eq!(state.value, Some(Value::V1));
+        assert!(out.is_empty());
+
+        let mut out = Out::new();
+        actor.on_timeout(peers[2], &mut state, &ConsensusTimer::ViewChange, &mut out);
+        assert_eq!(state.state, NodeState::ViewChanging);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_equivocation() {
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let actor = ConsensusActor {
+            fault_kind: FaultKind::Equivocate,
+            ..ConsensusActor::with_faults(peers.clone(), vec![3], 3)
+        };
+
+        // Equivocating nodes are faulty but, unlike crashed ones, still active
+        let mut out = Out::new();
+        let mut state: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[3], &None, &mut out));
+        assert!(state.is_faulty);
+        assert_eq!(state.state, NodeState::Init);
+
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V3, proposer: 0 };
+        actor.on_msg(peers[3], &mut state, peers[0], pre_prepare, &mut out);
+        for command in out.iter() {
+            let Command::Send(dst, msg) = command else { panic!("unexpected {:?}", command) };
+            let expected = if usize::from(*dst) % 2 == 0 { Value::V1 } else { Value::V2 };
+            match msg {
+                MessageType::Prepare { value, .. } | MessageType::Commit { value, .. } => {
+                    assert_eq!(*value, expected)
+                }
+                other => panic!("unexpected {:?}", other),
+            }
         }
+        assert_eq!(out.len(), 2 * peers.len());
     }
 
-    /// Check safety property: No premature decision
-    pub fn check_no_premature_decision(&self, state: &ConsensusNodeState) -> bool {
-        if state.state == NodeState::Decided {
-            if let Some(ref value) = state.value {
-                let commit_count = state.commit_count.get(value).unwrap_or(&0);
-                *commit_count >= state.quorum_size
-            } else {
-                false
+    #[test]
+    fn test_lying_leader() {
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let builder = || ConsensusActor::builder().peers(peers.clone()).quorum(3).fault_kind(FaultKind::LyingLeader);
+        let actor = builder().faulty(vec![0]).build().unwrap();
+
+        // The faulty primary tells each half of the peers a different story
+        let mut out = Out::new();
+        let state = actor.on_start(peers[0], &None, &mut out);
+        assert!(state.is_faulty);
+        for command in out.iter() {
+            let Command::Send(dst, msg) = command else { panic!("unexpected {:?}", command) };
+            let expected = if usize::from(*dst) % 2 == 0 { Value::V1 } else { Value::V2 };
+            assert_eq!(msg.value(), Some(&expected));
+        }
+        assert_eq!(out.len(), 4 * peers.len());
+
+        // Other faulty nodes equivocate, and the proposer has to be one of them
+        let mut out = Out::new();
+        let actor = builder().faulty(vec![0, 3]).build().unwrap();
+        let mut state = Cow::Owned(actor.on_start(peers[3], &None, &mut out));
+        assert!(out.is_empty());
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[3], &mut state, peers[0], pre_prepare, &mut out);
+        assert_eq!(out.len(), 2 * peers.len());
+        assert!(builder().faulty(vec![3]).build().is_err());
+    }
+
+    #[test]
+    fn test_fault_kinds() {
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let builder = || ConsensusActor::builder().peers(peers.clone()).quorum(3).faulty(vec![2, 3]);
+        let actor = builder().fault_kinds(HashMap::from([(3, FaultKind::Equivocate)])).build().unwrap();
+        assert_eq!(actor.fault_kind_of(2), FaultKind::Crash);
+        assert_eq!(actor.fault_kind_of(3), FaultKind::Equivocate);
+
+        // Node 2 may crash at any point, while node 3 votes for both values at once
+        let mut out = Out::new();
+        actor.on_start(peers[2], &None, &mut out);
+        assert!(out.iter().any(|command| matches!(command, Command::SetTimer(ConsensusTimer::Crash, _))));
+        let mut out = Out::new();
+        let mut state = Cow::Owned(actor.on_start(peers[3], &None, &mut out));
+        assert!(out.is_empty());
+        let pre_prepare = MessageType::PrePrepare { seq: 1, view: 0, value: Value::V1, proposer: 0 };
+        actor.on_msg(peers[3], &mut state, peers[0], pre_prepare, &mut out);
+        assert_eq!(out.len(), 2 * peers.len());
+
+        // Only faulty nodes have a fault kind, and only the proposer may lie as leader
+        assert!(builder().fault_kinds(HashMap::from([(1, FaultKind::Mute)])).build().is_err());
+        assert!(builder().fault_kinds(HashMap::from([(3, FaultKind::LyingLeader)])).build().is_err());
+    }
+
+    #[test]
+    fn test_consensus_model_checks() {
+        // Every message is delivered at most once, so the state space is finite
+        // and can be checked exhaustively
+        let model = ConsensusModel { num_values: 1, ..ConsensusModel::new(3, 0) };
+        let checker = model.clone().checker().spawn_bfs().join();
+        assert!(checker.is_done());
+        assert!(checker.unique_state_count() > 1);
+
+        // As in ConsensusActor, DECIDE is only honored after a local commit quorum
+        checker.assert_no_discovery("agreement");
+        checker.assert_no_discovery("no premature decision");
+        let init = model.init_states().remove(0);
+        let decide = ConsensusAction::Deliver((1, 2, MessageType::Decide { seq: FIRST_SEQ, view: 0, value: Value::V1 }));
+        assert!(model.next_state(&init, decide.clone()).is_none());
+        let mut committed = init;
+        committed.nodes[2].value = Some(Value::V1);
+        committed.nodes[2].state = NodeState::Committed;
+        let decided = model.next_state(&committed, decide).expect("a committed node decides");
+        assert_eq!(decided.nodes[2].state, NodeState::Decided);
+    }
+
+    #[test]
+    fn test_reachable_decisions() {
+        // Node 0 proposes all three values and pre-prepares whichever arrives first
+        let model = ConsensusModel::new(2, 0);
+        assert_eq!(model.reachable_decisions(), BTreeSet::from([Value::V1, Value::V2, Value::V3]));
+
+        let single = ConsensusModel { num_values: 1, ..model };
+        assert_eq!(single.reachable_decisions(), BTreeSet::from([Value::V1]));
+    }
+
+    #[test]
+    fn test_quorums_intersect() {
+        assert!(quorums_intersect(3, 2));
+        assert!(quorums_intersect(4, 3));
+        assert!(!quorums_intersect(4, 2));
+        assert!(!quorums_intersect(5, 2));
+
+        // Weighed by stake, a heavy node can make quorums overlap, or fail to
+        assert!(weighted_quorums_intersect(&[3, 1, 1, 1], 4));
+        assert!(!weighted_quorums_intersect(&[3, 1, 1, 1], 3));
+        assert!(weighted_tolerates_faults(&[1, 1, 1, 1], 3, 0, 1));
+        assert!(!weighted_tolerates_faults(&[3, 1, 1, 1], 4, 0, 1));
+        assert!(weighted_tolerates_faults(&[3, 1, 1, 1], 4, 0, 0));
+        assert!(!weighted_tolerates_faults(&[3, 1, 1, 1], 4, 1, 0));
+
+        // Every quorum the formulas produce intersects
+        for n in 1..10 {
+            for f in 0..n {
+                assert!(quorums_intersect(n, quorum_for(n, f, FaultModel::Crash)));
+                assert!(quorums_intersect(n, quorum_for(n, f, FaultModel::Byzantine)));
             }
-        } else {
-            true
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_builder() {
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .faulty(vec![3])
+            .quorum(3)
+            .fault_kind(FaultKind::Equivocate)
+            .num_values(1)
+            .build()
+            .unwrap();
+        assert_eq!(actor.faulty_nodes, vec![3]);
+        assert_eq!(actor.quorum_size, 3);
+        assert_eq!(actor.fault_kind, FaultKind::Equivocate);
+        assert_eq!(actor.initial_proposals, vec![Value::V1]);
+
+        // The proposer must have something to propose
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).initial_proposals(vec![]).build().is_err());
+
+        // A quorum larger than the peer set could never be reached, though the
+        // unchecked constructors take it
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(5).build().is_err());
+        assert_eq!(ConsensusActor::new(peers.clone(), 5).quorum_size, 5);
+        assert_eq!(ConsensusActor::with_faults(peers.clone(), vec![7], 5).faulty_nodes, vec![7]);
+
+        // but no peers, or a quorum of none, they refuse as well
+        assert!(std::panic::catch_unwind(|| ConsensusActor::new(Vec::new(), 1)).is_err());
+        assert!(std::panic::catch_unwind(|| ConsensusActor::with_faults(peers.clone(), vec![3], 0)).is_err());
+        assert!(ConsensusActor::builder().peers(Vec::new()).quorum(1).build().is_err());
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(0).build().is_err());
+
+        // So could an empty run of instances
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).instances(0).build().is_err());
+
+        // and a single decree of several instances
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).instances(2).build().is_err());
+        let log = ConsensusActor::builder().peers(peers.clone()).quorum(3).mode(ConsensusMode::Log).instances(2);
+        assert_eq!(log.build().unwrap().instances(), 1..3);
+
+        // Nor can any one node's own quorum exceed the peers
+        let oversized = HashMap::from([(1, 5)]);
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).quorum_sizes(oversized).build().is_err());
+        let stranger = HashMap::from([(4, 2)]);
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).quorum_sizes(stranger).build().is_err());
+
+        // Faulty nodes that aren't peers, or are listed twice, would test nothing
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).faulty(vec![99]).build().is_err());
+        assert!(ConsensusActor::builder().peers(peers.clone()).quorum(3).faulty(vec![3, 3]).build().is_err());
+
+        // Without peers nothing is ever sent, and an empty quorum is reached by nobody's votes
+        let no_peers = ConsensusActor::builder().quorum(1).build();
+        assert_eq!(no_peers.err().as_deref(), Some("at least one peer is needed"));
+        let empty_quorum = ConsensusActor::builder().peers(peers.clone()).build();
+        assert_eq!(empty_quorum.err().as_deref(), Some("quorum size must be at least 1"));
+        let zero = HashMap::from([(1, 0)]);
+        assert!(ConsensusActor::builder().peers(peers).quorum(3).quorum_sizes(zero).build().is_err());
+    }
 
     #[test]
-    fn test_initial_state() {
-        let state = ConsensusNodeState::new(0, 3);
-        assert_eq!(state.state, NodeState::Init);
-        assert_eq!(state.value, None);
-        assert!(!state.decided);
+    fn test_generic_values() {
+        // Any comparable, hashable value can be agreed on, here the proposer's numbers
+        let peers: Vec<Id> = (0..2).map(Id::from).collect();
+        let actor = ConsensusActorBuilder::<u32>::new()
+            .peers(peers.clone())
+            .quorum(2)
+            .initial_proposals(vec![7, 9])
+            .build()
+            .unwrap();
+        let model = ActorModel::new((), ())
+            .actors(peers.iter().map(|_| actor.clone()))
+            .init_network(Network::new_unordered_nonduplicating(vec![]))
+            .property(stateright::Expectation::Always, "agreement", |_, state| {
+                let decided: BTreeSet<_> = state.actor_states.iter().flat_map(|s| s.decisions.values()).collect();
+                decided.len() <= 1
+            })
+            .property(stateright::Expectation::Sometimes, "decided 9", |_, state| {
+                state.actor_states.iter().all(|s| s.decisions.get(&FIRST_SEQ) == Some(&9))
+            });
+        let checker = model.checker().spawn_bfs().join();
+        checker.assert_no_discovery("agreement");
+        assert!(checker.discovery("decided 9").is_some());
+
+        // With no default values to fall back on, equivocation needs two of its own
+        let builder = ConsensusActorBuilder::new().peers(peers).quorum(2).initial_proposals(vec!["a".to_string()]);
+        let equivocating = builder.clone().faulty(vec![1]).fault_kind(FaultKind::Equivocate);
+        assert!(equivocating.clone().build().is_err());
+        assert!(equivocating.equivocation("a".to_string(), "b".to_string()).build().is_ok());
     }
 
     #[test]
-    fn test_quorum_logic() {
-        let state = ConsensusNodeState::new(0, 3);
-        assert!(!state.has_quorum(2));
-        assert!(state.has_quorum(3));
-        assert!(state.has_quorum(4));
+    fn test_quorum_sizes() {
+        // Node 1 still believes in the old, smaller membership's quorum
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let actor = ConsensusActor::builder()
+            .peers(peers.clone())
+            .quorum(3)
+            .quorum_sizes(HashMap::from([(1, 2)]))
+            .build()
+            .unwrap();
+        assert_eq!(actor.quorum_size_of(0), 3);
+        assert_eq!(actor.quorum_size_of(1), 2);
+        assert_eq!(actor.on_start(peers[0], &None, &mut Out::new()).quorum_size, 3);
+        assert_eq!(actor.on_start(peers[1], &None, &mut Out::new()).quorum_size, 2);
+
+        // The uniform constructor gives every node the same belief
+        let uniform = ConsensusActor::new(peers, 3);
+        assert!((0..4).all(|node| uniform.quorum_size_of(node) == 3));
+    }
+
+    #[test]
+    fn test_weighted_quorum() {
+        // Node 0 holds half the stake of four nodes: with it, one more sender is
+        // enough; without it, all three others are still short
+        let stake = Stake { weights: HashMap::from([(0, 3)]), quorum_weight: Some(5) };
+        let mut node: ConsensusNodeState = ConsensusNodeState::new(1, 3);
+        assert_eq!(node.record_prepare(&stake, 1, &Value::V1, 1), Some(1));
+        assert_eq!(node.record_prepare(&stake, 1, &Value::V1, 0), Some(4));
+        assert!(!node.has_quorum(&stake, 4));
+        assert_eq!(node.record_prepare(&stake, 1, &Value::V1, 2), Some(5));
+        assert_eq!(node.reached_prepare_quorum.get(&(1, Value::V1)), Some(&true));
+        assert_eq!(node.record_commit(&stake, 1, &Value::V1, 1), Some(1));
+        assert_eq!(node.record_commit(&stake, 1, &Value::V1, 2), Some(2));
+        assert_eq!(node.record_commit(&stake, 1, &Value::V1, 3), Some(3));
+        assert!(!node.has_quorum(&stake, node.commit_weight(&stake, 1, &Value::V1)));
+
+        // Equal weights reduce to counting votes
+        let equal = Stake::default();
+        assert_eq!(equal.threshold(3), 3);
+        assert_eq!(equal.weight_of(&BTreeSet::from([0, 1, 2])), 3);
+
+        // The actor weighs every node's votes alike, and rejects unreachable weights
+        let peers: Vec<Id> = (0..4).map(Id::from).collect();
+        let builder = ConsensusActor::builder().peers(peers.clone()).quorum(3);
+        let actor = builder.clone().weights(HashMap::from([(0, 3)]), 5).build().unwrap();
+        assert_eq!(actor.stake, stake);
+        assert_eq!(actor.stake.weight_of(&BTreeSet::from([0, 1])), 4);
+        assert!(builder.clone().weights(HashMap::from([(0, 3)]), 7).build().is_err());
+        assert!(builder.clone().weights(HashMap::new(), 0).build().is_err());
+        assert!(builder.weights(HashMap::from([(4, 1)]), 3).build().is_err());
+    }
+
+    #[test]
+    fn test_max_tolerable_faults() {
+        assert_eq!(max_tolerable_faults(4, 3), 1);
+        assert_eq!(max_tolerable_faults(5, 3), 2);
+
+        // A quorum of every node leaves no fault budget at all
+        assert_eq!(max_tolerable_faults(2, 2), 0);
+        assert_eq!(max_tolerable_faults(5, 5), 0);
+        assert_eq!(max_tolerable_faults(3, 4), 0);
+    }
+
+    #[test]
+    fn test_tolerates_faults() {
+        // Crashes alone only need the rest to gather a quorum
+        assert!(tolerates_faults(3, 2, 1, 0));
+        assert!(!tolerates_faults(3, 2, 2, 0));
+
+        // One crashed and one equivocating: 4 nodes can't cope with any quorum,
+        // 6 can with a quorum of 4 but not of 5
+        assert!(!tolerates_faults(4, 3, 1, 1));
+        assert!(!tolerates_faults(4, 2, 1, 1));
+        assert!(tolerates_faults(6, 4, 1, 1));
+        assert!(!tolerates_faults(6, 5, 1, 1));
+        assert!(tolerates_faults(7, 5, 1, 1));
+    }
+
+    #[test]
+    fn test_quorum_for() {
+        // Crash faults: simple majority
+        assert_eq!(quorum_for(3, 1, FaultModel::Crash), 2);
+        assert_eq!(quorum_for(5, 2, FaultModel::Crash), 3);
+
+        // Byzantine faults: 2f + 1 when n = 3f + 1
+        assert_eq!(quorum_for(4, 1, FaultModel::Byzantine), 3);
+        assert_eq!(quorum_for(7, 2, FaultModel::Byzantine), 5);
+
+        // Never smaller than a majority, even with no faults
+        assert_eq!(quorum_for(5, 0, FaultModel::Byzantine), 3);
     }
 
     #[test]
     fn test_agreement_property() {
         let model = ConsensusModel::new(5, 2);
-        
-        let mut state1 = ConsensusNodeState::new(0, 3);
-        state1.value = Some(Value::V1);
-        state1.decided = true;
-        
-        let mut state2 = ConsensusNodeState::new(1, 3);
-        state2.value = Some(Value::V1);
-        state2.decided = true;
-        
-        assert!(model.check_agreement(&[state1.clone(), state2.clone()]));
+        let state1 = ConsensusNodeState::decided(0, 3, Value::V1);
+        let state2 = ConsensusNodeState::decided(1, 3, Value::V1);
+        assert!(model.check_agreement(&[state1.clone(), state2]));
         
         // Test violation
-        state2.value = Some(Value::V2);
+        let state2 = ConsensusNodeState::decided(1, 3, Value::V2);
         assert!(!model.check_agreement(&[state1, state2]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_decided_state() {
+        let state = ConsensusNodeState::decided(2, 3, Value::V1);
+        assert_eq!(state.state, NodeState::Decided);
+        assert_eq!(state.decisions, BTreeMap::from([(FIRST_SEQ, Value::V1)]));
+        assert_eq!(state.commit_count[&(FIRST_SEQ, Value::V1)], 3);
+        assert_eq!(state.commit_senders[&(FIRST_SEQ, Value::V1)], BTreeSet::from([0, 1, 2]));
+        assert_eq!(state.prepare_senders, state.commit_senders);
+        assert!(state.reached_commit_quorum[&(FIRST_SEQ, Value::V1)]);
+        assert_eq!((state.progress(), state.locked_value.clone()), (state.furthest.clone(), Some(Value::V1)));
+        assert!(ConsensusModel::new(5, 2).check_no_premature_decision(&state));
+    }
+
+    #[test]
+    fn test_state_json() {
+        // A node deep into a run, with every map populated, comes back unchanged
+        let mut state = ConsensusNodeState::decided(1, 2, Value::Custom(4));
+        state.record_prepare(&Stake::default(), 2, &Value::Bottom, 0);
+        state.view_change_votes.insert(1, BTreeSet::from([0, 1]));
+        state.checkpoint_votes.insert(FIRST_SEQ, BTreeSet::from([1]));
+        state.pending_prepares.insert(Value::V2, BTreeSet::from([0]));
+        state.checkpoint = Some(FIRST_SEQ);
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(serde_json::from_str::<ConsensusNodeState>(&json).unwrap(), state);
+
+        // in the documented layout, which saved traces rely on
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["state"], "Decided");
+        assert_eq!(json["value"], serde_json::json!({"Custom": 4}));
+        assert_eq!(json["prepare_count"], serde_json::json!([[[1, {"Custom": 4}], 2], [[2, "Bottom"], 1]]));
+        assert_eq!(json["commit_senders"], serde_json::json!([[[1, {"Custom": 4}], [0, 1]]]));
+        assert_eq!(json["pending_prepares"], serde_json::json!([["V2", [0]]]));
+        assert_eq!(json["view_change_votes"], serde_json::json!({"1": [0, 1]}));
+        assert_eq!(json["decisions"], serde_json::json!({"1": {"Custom": 4}}));
+        assert_eq!(json["furthest"], serde_json::json!([1, 0, "Decided"]));
+    }
+
+    #[test]
+    fn test_message_json() {
+        // Every message and timer, as traces record them
+        let value = Value::V1;
+        let messages = [
+            (MessageType::Propose { seq: 1, view: 0, value: value.clone(), proposer: 2 },
+             r#"{"Propose":{"seq":1,"view":0,"value":"V1","proposer":2}}"#),
+            (MessageType::PrePrepare { seq: 1, view: 0, value: value.clone(), proposer: 2 },
+             r#"{"PrePrepare":{"seq":1,"view":0,"value":"V1","proposer":2}}"#),
+            (MessageType::Prepare { seq: 2, view: 1, value: value.clone() }, r#"{"Prepare":{"seq":2,"view":1,"value":"V1"}}"#),
+            (MessageType::Commit { seq: 2, view: 1, value: Value::Bottom },
+             r#"{"Commit":{"seq":2,"view":1,"value":"Bottom"}}"#),
+            (MessageType::Decide { seq: 3, view: 0, value: Value::Custom(7) },
+             r#"{"Decide":{"seq":3,"view":0,"value":{"Custom":7}}}"#),
+            (MessageType::ViewChange(4), r#"{"ViewChange":4}"#),
+            (MessageType::NewView(4, value.clone()), r#"{"NewView":[4,"V1"]}"#),
+            (MessageType::Checkpoint(5), r#"{"Checkpoint":5}"#),
+        ];
+        for (msg, json) in messages {
+            assert_eq!(serde_json::to_string(&msg).unwrap(), json);
+            assert_eq!(serde_json::from_str::<MessageType>(json).unwrap(), msg);
+        }
+
+        let timers = [
+            (ConsensusTimer::ProposeValue(value), r#"{"ProposeValue":"V1"}"#),
+            (ConsensusTimer::ViewChange, r#""ViewChange""#),
+            (ConsensusTimer::Crash, r#""Crash""#),
+            (ConsensusTimer::HealPartition, r#""HealPartition""#),
+            (ConsensusTimer::Retry, r#""Retry""#),
+        ];
+        for (timer, json) in timers {
+            assert_eq!(serde_json::to_string(&timer).unwrap(), json);
+            assert_eq!(serde_json::from_str::<ConsensusTimer>(json).unwrap(), timer);
+        }
+        for state in NodeState::ALL {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(json, format!("{:?}", format!("{:?}", state)));
+            assert_eq!(serde_json::from_str::<NodeState>(&json).unwrap(), state);
+        }
+    }
+
+    /// A message for instance 1 in view 0: a PRE-PREPARE, PREPARE, COMMIT or DECIDE
+    /// from any of four nodes, for either of two values
+    fn delivery() -> impl Strategy<Value = (NodeId, MessageType)> {
+        let value = prop_oneof![Just(Value::V1), Just(Value::V2)];
+        (0..4usize, 0..4u8, value).prop_map(|(src, kind, value)| {
+            let msg = match kind {
+                0 => MessageType::PrePrepare { seq: 1, view: 0, value, proposer: 0 },
+                1 => MessageType::Prepare { seq: 1, view: 0, value },
+                2 => MessageType::Commit { seq: 1, view: 0, value },
+                _ => MessageType::Decide { seq: 1, view: 0, value },
+            };
+            (src, msg)
+        })
+    }
+
+    proptest! {
+        /// However messages arrive, repeated or out of order, a node never counts
+        /// more votes than there are peers, never loses a quorum it gathered, and
+        /// commits only after a quorum of PREPAREs
+        #[test]
+        fn test_counting_invariants(deliveries in prop::collection::vec(delivery(), 0..40)) {
+            let peers: Vec<Id> = (0..4).map(Id::from).collect();
+            let actor = ConsensusActor::builder().peers(peers.clone()).quorum(3).num_values(2).build().unwrap();
+            let mut node: Cow<ConsensusNodeState> = Cow::Owned(actor.on_start(peers[1], &None, &mut Out::new()));
+            let mut quorums = BTreeSet::new();
+
+            for (src, msg) in deliveries {
+                actor.on_msg(peers[1], &mut node, peers[src], msg, &mut Out::new());
+
+                for &count in node.prepare_count.values().chain(node.commit_count.values()) {
+                    prop_assert!(count <= peers.len());
+                }
+                for (key, senders) in &node.prepare_senders {
+                    if node.has_quorum(&actor.stake, actor.stake.weight_of(senders)) {
+                        quorums.insert(key.clone());
+                    }
+                }
+                for key in &quorums {
+                    let weight = node.prepare_senders.get(key).map_or(0, |senders| actor.stake.weight_of(senders));
+                    prop_assert!(node.has_quorum(&actor.stake, weight), "lost the PREPARE quorum for {:?}", key);
+                }
+                if matches!(node.state, NodeState::Committed | NodeState::Decided) {
+                    let value = node.value.clone().unwrap();
+                    prop_assert!(quorums.contains(&(1, value)));
+                }
+            }
+        }
+
+        /// Without weights, the votes counted make a quorum once there are
+        /// `quorum_size` of them, and further votes, repeated or not, never undo it
+        #[test]
+        fn test_has_quorum_monotonic(
+            quorum_size in 1..=4usize,
+            votes in prop::collection::vec((0..4usize, any::<bool>()), 0..20),
+        ) {
+            let mut node: ConsensusNodeState = ConsensusNodeState::new(0, quorum_size);
+            let stake = Stake::default();
+            let key = (FIRST_SEQ, Value::V1);
+            let (mut prepared, mut committed) = (false, false);
+            for (src, commit) in votes {
+                let (weight, count, quorum, reached) = if commit {
+                    let weight = node.record_commit(&stake, FIRST_SEQ, &Value::V1, src);
+                    (weight, node.commit_count[&key], &mut committed, node.reached_commit_quorum.contains_key(&key))
+                } else {
+                    let weight = node.record_prepare(&stake, FIRST_SEQ, &Value::V1, src);
+                    (weight, node.prepare_count[&key], &mut prepared, node.reached_prepare_quorum.contains_key(&key))
+                };
+                // Unweighted, a node's stake is the number of senders counted
+                prop_assert!(weight.is_none_or(|weight| weight == count as u64));
+                let has_quorum = node.has_quorum(&stake, count as u64);
+                prop_assert_eq!(has_quorum, count >= quorum_size);
+                prop_assert!(has_quorum || !*quorum, "lost a quorum of {}", quorum_size);
+                prop_assert_eq!(reached, has_quorum);
+                *quorum = has_quorum;
+            }
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use stateright::actor::*;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use crate::model::NodeId;
+
+/// Election term; each term has at most one leader
+pub type Term = u64;
+
+/// What part a node plays in the current term
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// Messages of Raft's leader election
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum RaftMsg {
+    /// A candidate asks for a vote in its term
+    RequestVote { term: Term },
+    /// A reply to a vote request, in the voter's (possibly newer) term
+    VoteResponse { term: Term, granted: bool },
+    /// A new leader announces itself; log entries aren't modeled
+    AppendEntries { term: Term },
+}
+
+/// Timer types for non-deterministic actions
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum RaftTimer {
+    Election,  // Fires if no leader has been heard from, and starts a new term
+}
+
+/// One node's view of the election
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct RaftState {
+    pub id: NodeId,
+    pub term: Term,
+    pub voted_for: Option<NodeId>,  // Whom this node voted for in `term`
+    pub role: Role,
+    pub votes: BTreeSet<NodeId>,  // Votes granted to this node as candidate in `term`
+    pub is_faulty: bool,
+}
+
+impl RaftState {
+    pub fn new(id: NodeId) -> Self {
+        RaftState {
+            id,
+            term: 0,
+            voted_for: None,
+            role: Role::Follower,
+            votes: BTreeSet::new(),
+            is_faulty: false,
+        }
+    }
+
+    /// Move on to a newer term seen in a message, as a follower without a vote cast
+    fn step_down(&mut self, term: Term) {
+        self.term = term;
+        self.role = Role::Follower;
+        self.voted_for = None;
+        self.votes.clear();
+    }
+}
+
+/// Actor implementing Raft's leader election
+#[derive(Clone)]
+pub struct RaftActor {
+    pub peers: Vec<Id>,
+    pub faulty_nodes: Vec<NodeId>,  // Nodes that crash before taking part
+    pub max_term: Term,             // Elections stop at this term, keeping the state space finite
+}
+
+impl RaftActor {
+    pub fn new(peers: Vec<Id>, max_term: Term) -> Self {
+        RaftActor { peers, faulty_nodes: Vec::new(), max_term }
+    }
+
+    /// Votes needed to win an election: a majority of all peers
+    pub fn majority(&self) -> usize {
+        self.peers.len() / 2 + 1
+    }
+
+    /// Having gathered a majority, become leader and tell every peer (ourselves
+    /// included, which changes nothing but records the win in the history)
+    fn win_if_elected(&self, state: &mut RaftState, o: &mut Out<Self>) {
+        if state.role == Role::Candidate && state.votes.len() >= self.majority() {
+            state.role = Role::Leader;
+            for &peer in &self.peers {
+                o.send(peer, RaftMsg::AppendEntries { term: state.term });
+            }
+        }
+    }
+}
+
+impl Actor for RaftActor {
+    type Msg = RaftMsg;
+    type State = RaftState;
+    type Timer = RaftTimer;
+    type Storage = ();
+    type Random = ();
+
+    fn on_start(&self, id: Id, _storage: &Option<Self::Storage>, o: &mut Out<Self>) -> Self::State {
+        let mut state = RaftState::new(usize::from(id));
+        // Faulty nodes are crashed from the start and never vote or stand
+        if self.faulty_nodes.contains(&state.id) {
+            state.is_faulty = true;
+        } else {
+            o.set_timer(RaftTimer::Election, model_timeout());
+        }
+        state
+    }
+
+    fn on_msg(
+        &self,
+        _id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        if state.is_faulty {
+            return;
+        }
+        let src = usize::from(src);
+        match msg {
+            RaftMsg::RequestVote { term } => {
+                // Grant at most one vote per term, first come first served
+                let mut new_state = state.as_ref().clone();
+                if term > new_state.term {
+                    new_state.step_down(term);
+                }
+                let granted = term == new_state.term && new_state.voted_for.is_none_or(|v| v == src);
+                if granted {
+                    new_state.voted_for = Some(src);
+                }
+                o.send(Id::from(src), RaftMsg::VoteResponse { term: new_state.term, granted });
+                if new_state != **state {
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            RaftMsg::VoteResponse { term, granted } => {
+                if term > state.term {
+                    state.to_mut().step_down(term);
+                } else if granted
+                    && term == state.term
+                    && state.role == Role::Candidate
+                    && !state.votes.contains(&src)
+                {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.votes.insert(src);
+                    self.win_if_elected(&mut new_state, o);
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            RaftMsg::AppendEntries { term } => {
+                // A leader for this term or a newer one exists: follow it
+                if term > state.term {
+                    state.to_mut().step_down(term);
+                } else if term == state.term && state.role == Role::Candidate {
+                    state.to_mut().role = Role::Follower;
+                }
+            }
+        }
+    }
+
+    fn on_timeout(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        timer: &Self::Timer,
+        o: &mut Out<Self>,
+    ) {
+        match timer {
+            RaftTimer::Election => {
+                // Stand for the next term, voting for ourselves
+                if state.is_faulty || state.role == Role::Leader || state.term >= self.max_term {
+                    return;
+                }
+                let mut new_state = state.as_ref().clone();
+                new_state.term += 1;
+                new_state.role = Role::Candidate;
+                new_state.voted_for = Some(new_state.id);
+                new_state.votes = BTreeSet::from([new_state.id]);
+                for &peer in self.peers.iter().filter(|&&peer| peer != id) {
+                    o.send(peer, RaftMsg::RequestVote { term: new_state.term });
+                }
+                self.win_if_elected(&mut new_state, o);
+                if new_state.term < self.max_term {
+                    o.set_timer(RaftTimer::Election, model_timeout());
+                }
+                *state = Cow::Owned(new_state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_election() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = RaftActor::new(peers.clone(), 2);
+
+        // Timing out starts term 1 with a vote for ourselves
+        let mut out = Out::new();
+        let start = actor.on_start(peers[0], &None, &mut out);
+        let mut candidate = Cow::Borrowed(&start);
+        let mut out = Out::new();
+        actor.on_timeout(peers[0], &mut candidate, &RaftTimer::Election, &mut out);
+        assert_eq!(candidate.term, 1);
+        assert_eq!(candidate.role, Role::Candidate);
+        assert_eq!(candidate.voted_for, Some(0));
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, RaftMsg::RequestVote { term: 1 }))).count(), 2);
+
+        // A follower grants its vote to the first candidate only
+        let mut follower = Cow::Owned(RaftState::new(1));
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut follower, peers[0], RaftMsg::RequestVote { term: 1 }, &mut out);
+        actor.on_msg(peers[1], &mut follower, peers[2], RaftMsg::RequestVote { term: 1 }, &mut out);
+        assert_eq!(follower.voted_for, Some(0));
+        let response = |to: Id| {
+            out.iter().find_map(|c| match c {
+                Command::Send(dst, RaftMsg::VoteResponse { granted, .. }) if *dst == to => Some(*granted),
+                _ => None,
+            })
+        };
+        assert_eq!(response(peers[0]), Some(true));
+        assert_eq!(response(peers[2]), Some(false));
+
+        // One vote besides its own is a majority of three
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut candidate, peers[1], RaftMsg::VoteResponse { term: 1, granted: true }, &mut out);
+        assert_eq!(candidate.role, Role::Leader);
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, RaftMsg::AppendEntries { term: 1 }))).count(), 3);
+    }
+
+    #[test]
+    fn test_step_down() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = RaftActor::new(peers.clone(), 3);
+        let mut leader = RaftState::new(0);
+        leader.term = 1;
+        leader.role = Role::Leader;
+        leader.voted_for = Some(0);
+        let mut leader = Cow::Owned(leader);
+
+        // A newer term's candidate deposes the leader and gets its vote
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut leader, peers[2], RaftMsg::RequestVote { term: 2 }, &mut out);
+        assert_eq!(leader.term, 2);
+        assert_eq!(leader.role, Role::Follower);
+        assert_eq!(leader.voted_for, Some(2));
+
+        // Leaders don't stand again, and nobody stands past the last term
+        let mut leader = Cow::Owned(RaftState { role: Role::Leader, ..RaftState::new(1) });
+        actor.on_timeout(peers[1], &mut leader, &RaftTimer::Election, &mut out);
+        assert_eq!(leader.term, 0);
+        let mut last = Cow::Owned(RaftState { term: 3, ..RaftState::new(1) });
+        actor.on_timeout(peers[1], &mut last, &RaftTimer::Election, &mut out);
+        assert_eq!(last.term, 3);
+    }
+}
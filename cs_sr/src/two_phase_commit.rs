@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use stateright::actor::*;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use crate::model::{NodeId, Value};
+
+/// Messages of two-phase commit (2PC)
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum TpcMsg {
+    /// The coordinator asks a participant whether it can commit the transaction
+    Prepare,
+    /// A participant can commit, and will wait to be told the outcome
+    VoteYes,
+    /// A participant can't commit, and has already aborted
+    VoteNo,
+    /// Every participant voted yes: commit the transaction
+    Commit,
+    /// Some participant voted no, or the coordinator gave up waiting: abort
+    Abort,
+}
+
+/// Timer types for non-deterministic actions
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum TpcTimer {
+    Vote(bool),  // A prepared participant makes up its mind, yes or no, whichever fires first
+    Timeout,     // The coordinator stops waiting for votes and aborts
+}
+
+/// One node's view of the transaction, as coordinator or participant
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct TpcState {
+    pub id: NodeId,
+    pub vote: Option<bool>,      // A participant's vote, once cast
+    pub yes_votes: BTreeSet<NodeId>,  // Participants the coordinator has heard a yes from
+    pub outcome: Option<Value>,  // The committed value, or `Value::Bottom` once aborted
+    pub is_faulty: bool,
+}
+
+impl TpcState {
+    pub fn new(id: NodeId) -> Self {
+        TpcState { id, vote: None, yes_votes: BTreeSet::new(), outcome: None, is_faulty: false }
+    }
+
+    /// Whether this node has aborted the transaction
+    pub fn aborted(&self) -> bool {
+        self.outcome.as_ref().is_some_and(Value::is_bottom)
+    }
+}
+
+/// Actor implementing two-phase commit: the first peer coordinates, and the
+/// others are participants that each vote on committing `value`
+#[derive(Clone)]
+pub struct TpcActor {
+    pub peers: Vec<Id>,
+    pub faulty_nodes: Vec<NodeId>,  // Participants that crash before voting
+    pub value: Value,               // What committing the transaction decides
+}
+
+impl TpcActor {
+    pub fn new(peers: Vec<Id>, value: Value) -> Self {
+        TpcActor { peers, faulty_nodes: Vec::new(), value }
+    }
+
+    pub fn coordinator(&self) -> Id {
+        self.peers[0]
+    }
+
+    pub fn participants(&self) -> &[Id] {
+        &self.peers[1..]
+    }
+
+    /// Settle the coordinator's outcome and tell every participant
+    fn decide(&self, state: &mut TpcState, commit: bool, o: &mut Out<Self>) {
+        state.outcome = Some(if commit { self.value.clone() } else { Value::Bottom });
+        let msg = if commit { TpcMsg::Commit } else { TpcMsg::Abort };
+        for &peer in self.participants() {
+            o.send(peer, msg.clone());
+        }
+        o.cancel_timer(TpcTimer::Timeout);
+    }
+}
+
+impl Actor for TpcActor {
+    type Msg = TpcMsg;
+    type State = TpcState;
+    type Timer = TpcTimer;
+    type Storage = ();
+    type Random = ();
+
+    fn on_start(&self, id: Id, _storage: &Option<Self::Storage>, o: &mut Out<Self>) -> Self::State {
+        let mut state = TpcState::new(usize::from(id));
+        if self.faulty_nodes.contains(&state.id) {
+            state.is_faulty = true;
+        } else if id == self.coordinator() {
+            for &peer in self.participants() {
+                o.send(peer, TpcMsg::Prepare);
+            }
+            o.set_timer(TpcTimer::Timeout, model_timeout());
+        }
+        state
+    }
+
+    fn on_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        if state.is_faulty {
+            return;
+        }
+        match msg {
+            TpcMsg::Prepare => {
+                // Either vote may fire; the other is cancelled when one does
+                if src == self.coordinator() && state.vote.is_none() {
+                    o.set_timer(TpcTimer::Vote(true), model_timeout());
+                    o.set_timer(TpcTimer::Vote(false), model_timeout());
+                }
+            }
+
+            TpcMsg::VoteYes => {
+                // Commit once every participant is for it
+                if id == self.coordinator() && state.outcome.is_none() && !state.yes_votes.contains(&usize::from(src)) {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.yes_votes.insert(usize::from(src));
+                    if new_state.yes_votes.len() == self.participants().len() {
+                        self.decide(&mut new_state, true, o);
+                    }
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            TpcMsg::VoteNo => {
+                // A single no aborts the transaction for everyone
+                if id == self.coordinator() && state.outcome.is_none() {
+                    self.decide(state.to_mut(), false, o);
+                }
+            }
+
+            TpcMsg::Commit | TpcMsg::Abort => {
+                // Only the coordinator's word settles a participant that voted yes
+                if src == self.coordinator() && state.outcome.is_none() {
+                    let value = if msg == TpcMsg::Commit { self.value.clone() } else { Value::Bottom };
+                    state.to_mut().outcome = Some(value);
+                }
+            }
+        }
+    }
+
+    fn on_timeout(
+        &self,
+        _id: Id,
+        state: &mut Cow<Self::State>,
+        timer: &Self::Timer,
+        o: &mut Out<Self>,
+    ) {
+        match timer {
+            TpcTimer::Vote(yes) => {
+                // A participant voting no may abort at once, as it can't be outvoted
+                if state.vote.is_none() {
+                    let mut new_state = state.as_ref().clone();
+                    new_state.vote = Some(*yes);
+                    o.cancel_timer(TpcTimer::Vote(!yes));
+                    if *yes {
+                        o.send(self.coordinator(), TpcMsg::VoteYes);
+                    } else {
+                        new_state.outcome = Some(Value::Bottom);
+                        o.send(self.coordinator(), TpcMsg::VoteNo);
+                    }
+                    *state = Cow::Owned(new_state);
+                }
+            }
+
+            TpcTimer::Timeout => {
+                // Not every participant has voted yes in time: abort
+                if state.outcome.is_none() {
+                    self.decide(state.to_mut(), false, o);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = TpcActor::new(peers.clone(), Value::V1);
+
+        // The coordinator asks every participant and waits
+        let mut out = Out::new();
+        let start = actor.on_start(peers[0], &None, &mut out);
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, TpcMsg::Prepare))).count(), 2);
+        let mut coordinator = Cow::Borrowed(&start);
+
+        // A prepared participant may vote either way, then sticks to its vote
+        let mut participant = Cow::Owned(TpcState::new(1));
+        let mut out = Out::new();
+        actor.on_msg(peers[1], &mut participant, peers[0], TpcMsg::Prepare, &mut out);
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::SetTimer(TpcTimer::Vote(_), _))).count(), 2);
+        let mut out = Out::new();
+        actor.on_timeout(peers[1], &mut participant, &TpcTimer::Vote(true), &mut out);
+        assert_eq!(participant.vote, Some(true));
+        assert_eq!(participant.outcome, None);
+        assert!(out.iter().any(|c| matches!(c, Command::Send(dst, TpcMsg::VoteYes) if *dst == peers[0])));
+        actor.on_timeout(peers[1], &mut participant, &TpcTimer::Vote(false), &mut out);
+        assert_eq!(participant.vote, Some(true));
+
+        // Two yes votes, one repeated, commit once both participants are in
+        let mut out = Out::new();
+        for voter in [1, 1, 2] {
+            actor.on_msg(peers[0], &mut coordinator, peers[voter], TpcMsg::VoteYes, &mut out);
+        }
+        assert_eq!(coordinator.outcome, Some(Value::V1));
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, TpcMsg::Commit))).count(), 2);
+
+        actor.on_msg(peers[1], &mut participant, peers[0], TpcMsg::Commit, &mut out);
+        assert_eq!(participant.outcome, Some(Value::V1));
+    }
+
+    #[test]
+    fn test_abort() {
+        let peers: Vec<Id> = (0..3).map(Id::from).collect();
+        let actor = TpcActor::new(peers.clone(), Value::V1);
+
+        // Voting no aborts the participant straight away, and then the coordinator
+        let mut participant = Cow::Owned(TpcState::new(2));
+        let mut out = Out::new();
+        actor.on_timeout(peers[2], &mut participant, &TpcTimer::Vote(false), &mut out);
+        assert!(participant.aborted());
+        let mut coordinator = Cow::Owned(TpcState::new(0));
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut coordinator, peers[2], TpcMsg::VoteNo, &mut out);
+        assert!(coordinator.aborted());
+        assert_eq!(out.iter().filter(|c| matches!(c, Command::Send(_, TpcMsg::Abort))).count(), 2);
+
+        // A late yes changes nothing once the outcome is settled
+        let mut out = Out::new();
+        actor.on_msg(peers[0], &mut coordinator, peers[1], TpcMsg::VoteYes, &mut out);
+        assert!(coordinator.aborted() && out.is_empty());
+
+        // Nor does a timeout, which otherwise aborts a coordinator still waiting
+        actor.on_timeout(peers[0], &mut coordinator, &TpcTimer::Timeout, &mut out);
+        assert!(out.is_empty());
+        let mut waiting = Cow::Owned(TpcState::new(0));
+        actor.on_timeout(peers[0], &mut waiting, &TpcTimer::Timeout, &mut out);
+        assert!(waiting.aborted());
+
+        // Only the coordinator's outcome counts
+        let mut participant = Cow::Owned(TpcState::new(1));
+        actor.on_msg(peers[1], &mut participant, peers[2], TpcMsg::Commit, &mut Out::new());
+        assert_eq!(participant.outcome, None);
+    }
+}
@@ -0,0 +1,36 @@
+use cs_sr::model::{ConsensusActor, FaultKind};
+use cs_sr::scenario::build_actor_model;
+use cs_sr::scenario::{run_direct_scenario, run_raft_scenario, run_tpc_scenario};
+use cs_sr::{run_scenario, ScenarioConfig};
+use stateright::actor::{Actor, Id, Out};
+
+#[test]
+fn test_downstream_use() {
+    // The actor can be driven directly, as in a harness of one's own
+    let peers: Vec<Id> = (0..2).map(Id::from).collect();
+    let actor = ConsensusActor::builder().peers(peers.clone()).quorum(2).num_values(1).build().unwrap();
+    let mut out = Out::new();
+    actor.on_start(peers[0], &None, &mut out);
+    assert_eq!(out.len(), peers.len());
+
+    // and whole scenarios checked through the library
+    let report = run_scenario(&ScenarioConfig { num_values: 1, ..ScenarioConfig::new(2, 0) }).unwrap();
+    assert!(report.violations.is_empty());
+
+    // A scenario the checker can't build is an error, not a panic
+    let invalid = [
+        ScenarioConfig { proposer_id: 5, ..ScenarioConfig::new(3, 0) },
+        ScenarioConfig { num_values: 0, ..ScenarioConfig::new(3, 0) },
+        ScenarioConfig { observers: [0].into(), ..ScenarioConfig::new(3, 0) },
+        ScenarioConfig { fault_kind: FaultKind::LyingLeader, ..ScenarioConfig::new(4, 1) },
+        ScenarioConfig::new(2, 3),
+    ];
+    for config in &invalid {
+        assert!(config.validate().is_err());
+        assert!(build_actor_model(config).is_err());
+        assert!(run_scenario(config).is_err());
+        assert!(run_direct_scenario(config).is_err());
+        assert!(run_raft_scenario(config, 1).is_err());
+        assert!(run_tpc_scenario(config).is_err());
+    }
+}